@@ -0,0 +1,207 @@
+use crate::db::{self, SessionMessage, SessionState, SessionWithInitiator};
+use crate::helpers::{self, InTopic};
+use crate::i18n::{self, Texts};
+use rusqlite::OptionalExtension;
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{Chat, InlineKeyboardButton, InlineKeyboardMarkup, ParseMode},
+    utils::markdown,
+};
+use tokio_rusqlite::Connection;
+
+/// Longest title shown on the list buttons.
+const BUTTON_TITLE_LENGTH: usize = 20;
+
+pub fn is_active_data(data: &str) -> bool {
+    data.starts_with("act_")
+}
+
+pub async fn show(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    texts: &Texts,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (text, keyboard) = render_list(&conn, &msg.chat, helpers::topic_id(&msg), texts).await?;
+
+    bot.parse_mode(ParseMode::MarkdownV2)
+        .send_message(msg.chat.id, text)
+        .reply_markup(keyboard)
+        .disable_web_page_preview(true)
+        .in_topic(helpers::topic_id(&msg))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let CallbackQuery {
+        id: callback_id,
+        data,
+        from: user,
+        message,
+        ..
+    } = q;
+    let message = message.unwrap();
+
+    let Some((action, session_id)) = data
+        .as_deref()
+        .and_then(|data| data.strip_prefix("act_"))
+        .and_then(|data| data.split_once('_'))
+        .and_then(|(action, id)| Some((action.to_string(), id.parse::<usize>().ok()?)))
+    else {
+        bot.answer_callback_query(callback_id).await?;
+        return Ok(());
+    };
+
+    let from = user.clone();
+    let user_db_id = conn.call(move |conn| db::upsert_user(conn, &user)).await?;
+
+    let chat_id = message.chat.id;
+    let (session, config) = conn
+        .call(move |conn| {
+            Ok((
+                db::find_session_by_id(conn, session_id).optional()?,
+                db::find_chat_config(conn, chat_id)?,
+            ))
+        })
+        .await?;
+    let texts = i18n::texts(i18n::resolve(config.language, Some(&from)));
+
+    // Sessions of other chats are as unknown as deleted ones.
+    let Some(mut session) = session.filter(|session| session.chat_id() == Some(chat_id)) else {
+        bot.answer_callback_query(callback_id)
+            .text(texts.session_not_found)
+            .show_alert(true)
+            .await?;
+
+        return Ok(());
+    };
+
+    // The same rules as for the buttons under the session message.
+    let allowed = match action.as_str() {
+        "cancel" => crate::can_cancel_session(&bot, &session, from.id, user_db_id).await?,
+        _ => crate::can_manage_session(&bot, &config, &session, from.id, user_db_id).await?,
+    };
+
+    let notice = if !allowed {
+        Some(texts.no_manage_rights)
+    } else if session.state != SessionState::Open {
+        Some(texts.session_finished)
+    } else {
+        None
+    };
+
+    if let Some(notice) = notice {
+        bot.answer_callback_query(callback_id)
+            .text(notice)
+            .show_alert(true)
+            .await?;
+
+        return Ok(());
+    }
+
+    bot.answer_callback_query(callback_id).await?;
+
+    match action.as_str() {
+        "finish" => crate::finish_session(bot.clone(), &conn, &mut session, &config).await?,
+        "cancel" => crate::cancel_session(bot.clone(), &conn, &mut session, &config).await?,
+        _ => return Ok(()),
+    }
+
+    let thread_id = helpers::topic_id(&message);
+    let (text, keyboard) = render_list(&conn, &message.chat, thread_id, texts).await?;
+
+    bot.parse_mode(ParseMode::MarkdownV2)
+        .edit_message_text(message.chat.id, message.id, text)
+        .reply_markup(keyboard)
+        .disable_web_page_preview(true)
+        .await?;
+
+    Ok(())
+}
+
+/// Sessions of the topic only, when the list is shown in one.
+async fn render_list(
+    conn: &Connection,
+    chat: &Chat,
+    thread_id: Option<i32>,
+    texts: &Texts,
+) -> Result<(String, InlineKeyboardMarkup), tokio_rusqlite::Error> {
+    let chat_id = chat.id;
+    let sessions = conn
+        .call(move |conn| db::find_active_sessions(conn, chat_id, thread_id))
+        .await?;
+
+    if sessions.is_empty() {
+        return Ok((
+            markdown::escape(texts.no_active_sessions),
+            InlineKeyboardMarkup::default(),
+        ));
+    }
+
+    let lines: Vec<String> = sessions
+        .iter()
+        .map(|session| render_session(chat, session, texts))
+        .collect();
+
+    let keyboard = sessions
+        .iter()
+        .map(|session| {
+            let title: String = session.title.chars().take(BUTTON_TITLE_LENGTH).collect();
+
+            vec![
+                InlineKeyboardButton::callback(
+                    (texts.finish_session_button)(&title),
+                    format!("act_finish_{}", session.session_id),
+                ),
+                InlineKeyboardButton::callback(
+                    texts.cancel_session_button,
+                    format!("act_cancel_{}", session.session_id),
+                ),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let text = format!(
+        "{}\n\n{}",
+        markdown::bold(markdown::escape(texts.active_heading).as_str()),
+        lines.join("\n\n")
+    );
+
+    Ok((text, InlineKeyboardMarkup::new(keyboard)))
+}
+
+fn render_session(chat: &Chat, session: &SessionWithInitiator, texts: &Texts) -> String {
+    let link = match session.message {
+        SessionMessage::Chat(_, message_id) => helpers::message_link(chat, message_id),
+        SessionMessage::Inline(_) => None,
+    };
+
+    let title = match link {
+        Some(link) => markdown::link(&link, markdown::escape(&session.title).as_str()),
+        None => markdown::escape(&session.title),
+    };
+
+    format!(
+        "{}\n{}",
+        title,
+        markdown::escape(
+            format!(
+                "{}: {}",
+                texts.initiator,
+                helpers::make_username_line(
+                    &session.initiator_first_name,
+                    &session.initiator_last_name,
+                    &session.initiator_username,
+                )
+            )
+            .as_str()
+        )
+    )
+}
@@ -0,0 +1,95 @@
+use crate::db::{self, SessionMessage, SessionState, SessionWithInitiator};
+use crate::helpers::{self, InTopic};
+use crate::i18n::Texts;
+use std::error::Error;
+use teloxide::{prelude::*, types::ParseMode};
+use tokio_rusqlite::Connection;
+
+/// Reposts a session at the bottom of the chat: the session the command
+/// replies to, otherwise the latest open one of the topic. Voting continues
+/// in the new message, the old one loses its keyboard.
+pub async fn bump(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    texts: &Texts,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = msg.chat.id;
+    let thread_id = helpers::topic_id(&msg);
+    let replied = msg
+        .reply_to_message()
+        .map(|reply| SessionMessage::Chat(chat_id, reply.id));
+
+    // Messages of forum topics reply to the topic's first message, so a
+    // reply without a session behind it falls back to the latest session.
+    let session = conn
+        .call(move |conn| {
+            let session = match replied {
+                Some(message) => db::find_session_by_message(conn, message)?,
+                None => None,
+            };
+
+            match session {
+                Some(session) => Ok(Some(session)),
+                None => Ok(db::find_active_sessions(conn, chat_id, thread_id)?.pop()),
+            }
+        })
+        .await?
+        .filter(|session| matches!(session.state, SessionState::Open | SessionState::Revealed));
+
+    let Some(session) = session else {
+        bot.send_message(chat_id, texts.nothing_to_bump)
+            .in_topic(thread_id)
+            .await?;
+        return Ok(());
+    };
+
+    let SessionMessage::Chat(_, old_message_id) = session.message else {
+        return Ok(());
+    };
+
+    let session_id = session.session_id;
+    let (config, estimations) = conn
+        .call(move |conn| {
+            Ok((
+                db::find_session_config(conn, Some(chat_id))?,
+                db::find_estimations(conn, session_id)?,
+            ))
+        })
+        .await?;
+
+    // The keyboard is added once the session is bound to the new message,
+    // earlier presses would not find their session.
+    let sent = bot
+        .clone()
+        .parse_mode(ParseMode::MarkdownV2)
+        .send_message(
+            chat_id,
+            crate::render_text(&session, &config, Some(estimations.clone())),
+        )
+        .in_topic(session.thread_id)
+        .await?;
+
+    let new_message_id = sent.id;
+    conn.call(move |conn| db::move_session(conn, session_id, new_message_id))
+        .await?;
+
+    let session = SessionWithInitiator {
+        message: SessionMessage::Chat(chat_id, new_message_id),
+        ..session
+    };
+    crate::send_response_message(
+        bot.clone(),
+        &conn,
+        &session,
+        &config,
+        Some(estimations),
+        false,
+    )
+    .await?;
+
+    // The old message may already be deleted, there is nothing to strip then.
+    let _ = bot.edit_message_reply_markup(chat_id, old_message_id).await;
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use crate::db::Confidence;
+
+/// Prefix of the payload layout, changed whenever the layout changes so
+/// buttons of older messages are recognised as outdated instead of misread.
+const VERSION: &str = "v1";
+
+/// What a button of a session message does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionAction {
+    Vote(usize),
+    Confidence(Confidence),
+    Restart,
+    Finish,
+    Next,
+    Cancel,
+    Delete,
+}
+
+/// Callback data of a session button: `v1:<session id>:<action>[:<value>]`,
+/// well below the 64 bytes Telegram allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionCallback {
+    pub session_id: usize,
+    pub action: SessionAction,
+}
+
+impl SessionCallback {
+    pub fn new(session_id: usize, action: SessionAction) -> Self {
+        SessionCallback { session_id, action }
+    }
+
+    pub fn encode(&self) -> String {
+        let action = match self.action {
+            SessionAction::Vote(value) => format!("vote:{}", value),
+            SessionAction::Confidence(confidence) => format!("conf:{}", confidence.as_str()),
+            SessionAction::Restart => "restart".to_string(),
+            SessionAction::Finish => "finish".to_string(),
+            SessionAction::Next => "next".to_string(),
+            SessionAction::Cancel => "cancel".to_string(),
+            SessionAction::Delete => "delete".to_string(),
+        };
+
+        format!("{}:{}:{}", VERSION, self.session_id, action)
+    }
+
+    /// `None` for data of another version, of other buttons or malformed.
+    pub fn decode(data: &str) -> Option<Self> {
+        let mut parts = data.split(':');
+
+        if parts.next()? != VERSION {
+            return None;
+        }
+
+        let session_id = parts.next()?.parse().ok()?;
+        let action = match (parts.next()?, parts.next()) {
+            ("vote", Some(value)) => SessionAction::Vote(value.parse().ok()?),
+            ("conf", Some(confidence)) => SessionAction::Confidence(confidence.parse().ok()?),
+            ("restart", None) => SessionAction::Restart,
+            ("finish", None) => SessionAction::Finish,
+            ("next", None) => SessionAction::Next,
+            ("cancel", None) => SessionAction::Cancel,
+            ("delete", None) => SessionAction::Delete,
+            _ => return None,
+        };
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(SessionCallback { session_id, action })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_action() {
+        let actions = [
+            SessionAction::Vote(0),
+            SessionAction::Vote(13),
+            SessionAction::Confidence(Confidence::Low),
+            SessionAction::Confidence(Confidence::High),
+            SessionAction::Restart,
+            SessionAction::Finish,
+            SessionAction::Next,
+            SessionAction::Cancel,
+            SessionAction::Delete,
+        ];
+
+        for action in actions {
+            let callback = SessionCallback::new(42, action);
+
+            assert_eq!(SessionCallback::decode(&callback.encode()), Some(callback));
+        }
+    }
+
+    #[test]
+    fn encodes_the_documented_layout() {
+        assert_eq!(
+            SessionCallback::new(7, SessionAction::Vote(5)).encode(),
+            "v1:7:vote:5"
+        );
+        assert_eq!(
+            SessionCallback::new(7, SessionAction::Confidence(Confidence::Medium)).encode(),
+            "v1:7:conf:medium"
+        );
+        assert_eq!(
+            SessionCallback::new(7, SessionAction::Finish).encode(),
+            "v1:7:finish"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_data() {
+        for data in [
+            "",
+            "v1",
+            "v1:",
+            "v1:7",
+            "v1:x:finish",
+            "v1:-1:finish",
+            "v1:7:vote",
+            "v1:7:vote:five",
+            "v1:7:conf:unsure",
+            "v1:7:finish:now",
+            "v1:7:vote:5:6",
+            "v1:7:bet:5",
+        ] {
+            assert_eq!(SessionCallback::decode(data), None, "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        assert_eq!(SessionCallback::decode("v2:7:finish"), None);
+        assert_eq!(SessionCallback::decode("V1:7:finish"), None);
+    }
+
+    #[test]
+    fn rejects_legacy_data() {
+        for data in ["5", "restart", "finish", "next", "conf_low", "cmp_left"] {
+            assert_eq!(SessionCallback::decode(data), None, "{:?}", data);
+        }
+    }
+}
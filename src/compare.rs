@@ -0,0 +1,418 @@
+use crate::constants::{COMPARE_ANSWER_BUTTONS, COMPARE_FUNC_BUTTONS};
+use crate::db::{self, Comparison, ComparisonAnswer, Language};
+use crate::helpers::{self, InTopic};
+use crate::i18n::{self, Texts};
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{Chat, InlineKeyboardButton, InlineKeyboardMarkup},
+};
+use tokio_rusqlite::Connection;
+
+/// Every pair shows two items, so the number of pairs grows quadratically.
+const MAX_ITEMS: usize = 8;
+const INITIAL_RATING: f64 = 1000.0;
+const ELO_K: f64 = 32.0;
+
+pub fn is_compare_data(data: &str) -> bool {
+    data.starts_with("cmp_")
+}
+
+pub async fn start(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    payload: String,
+    language: Language,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let texts = i18n::texts(language);
+
+    let items: Vec<String> = payload
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+
+    if items.len() < 2 || items.len() > MAX_ITEMS {
+        bot.send_message(msg.chat.id, (texts.compare_usage)(MAX_ITEMS))
+            .in_topic(helpers::topic_id(&msg))
+            .await?;
+
+        return Ok(());
+    }
+
+    let user = msg.from().unwrap().clone();
+    let initiator_id = conn.call(move |conn| db::upsert_user(conn, &user)).await?;
+
+    let sent = bot
+        .send_message(msg.chat.id, render_pair(&items, 0, 0, texts))
+        .reply_markup(make_keyboard(0, texts))
+        .in_topic(helpers::topic_id(&msg))
+        .await?;
+
+    conn.call(move |conn| {
+        db::save_comparison(conn, sent.chat.id, sent.id, initiator_id, language, items)
+    })
+    .await?;
+
+    Ok(())
+}
+
+pub async fn callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let CallbackQuery {
+        id: callback_id,
+        data,
+        from: user,
+        message,
+        ..
+    } = q;
+    let Message {
+        chat: Chat { id: chat_id, .. },
+        id: message_id,
+        ..
+    } = message.unwrap();
+    let data = data.unwrap_or_default();
+
+    let Some(comparison) = conn
+        .call(move |conn| db::find_comparison(conn, chat_id, message_id))
+        .await?
+    else {
+        let config = conn
+            .call(move |conn| db::find_chat_config(conn, chat_id))
+            .await?;
+        let texts = i18n::texts(i18n::resolve(config.language, Some(&user)));
+
+        bot.answer_callback_query(callback_id)
+            .text(texts.unknown_comparison)
+            .show_alert(true)
+            .await?;
+        bot.edit_message_reply_markup(chat_id, message_id).await?;
+
+        return Ok(());
+    };
+
+    let user_db_id = conn.call(move |conn| db::upsert_user(conn, &user)).await?;
+
+    let comparison_id = comparison.id;
+    let current_pair = comparison.current_pair;
+    let pairs_count = pairs(comparison.items.len()).len();
+    let texts = i18n::texts(comparison.language);
+
+    if comparison.finished == 1 {
+        bot.answer_callback_query(callback_id)
+            .text(texts.comparison_finished)
+            .await?;
+
+        return Ok(());
+    }
+
+    // Buttons carry the pair they were shown with, so a click that arrives
+    // after the comparison moved on does not count towards the next pair.
+    let Some((action, pair)) = parse_data(&data) else {
+        bot.answer_callback_query(callback_id)
+            .text(texts.unknown_button)
+            .show_alert(true)
+            .await?;
+
+        return Ok(());
+    };
+
+    if pair != current_pair {
+        bot.answer_callback_query(callback_id)
+            .text(texts.comparison_pair_passed)
+            .show_alert(true)
+            .await?;
+
+        return Ok(());
+    }
+
+    match action {
+        "cmp_next" | "cmp_finish" => {
+            if comparison.initiator_db_id != user_db_id {
+                bot.answer_callback_query(callback_id)
+                    .text(texts.compare_initiator_only)
+                    .show_alert(true)
+                    .await?;
+
+                return Ok(());
+            }
+
+            bot.answer_callback_query(callback_id).await?;
+
+            let finished = action == "cmp_finish" || current_pair + 1 >= pairs_count;
+
+            let advanced = conn
+                .call(move |conn| {
+                    db::advance_comparison(conn, comparison_id, current_pair, finished)
+                })
+                .await?;
+
+            // Another click of the initiator has already switched the pair.
+            if !advanced {
+                return Ok(());
+            }
+
+            if finished {
+                let (answers, config) = conn
+                    .call(move |conn| {
+                        Ok((
+                            db::find_comparison_answers(conn, comparison_id)?,
+                            db::find_chat_config(conn, chat_id)?,
+                        ))
+                    })
+                    .await?;
+
+                bot.edit_message_text(
+                    chat_id,
+                    message_id,
+                    render_ranking(&comparison, &answers, config.deck.cards(), texts),
+                )
+                .await?;
+            } else {
+                bot.edit_message_text(
+                    chat_id,
+                    message_id,
+                    render_pair(&comparison.items, current_pair + 1, 0, texts),
+                )
+                .reply_markup(make_keyboard(current_pair + 1, texts))
+                .await?;
+            }
+        }
+        outcome if COMPARE_ANSWER_BUTTONS.contains(&outcome) => {
+            bot.answer_callback_query(callback_id).await?;
+
+            let outcome = outcome.trim_start_matches("cmp_").to_string();
+
+            let answers_count = conn
+                .call(move |conn| {
+                    db::save_comparison_answer(
+                        conn,
+                        comparison_id,
+                        current_pair,
+                        user_db_id,
+                        outcome,
+                    )?;
+                    db::count_comparison_answers(conn, comparison_id, current_pair)
+                })
+                .await?;
+
+            let _ = bot
+                .edit_message_text(
+                    chat_id,
+                    message_id,
+                    render_pair(&comparison.items, current_pair, answers_count, texts),
+                )
+                .reply_markup(make_keyboard(current_pair, texts))
+                .await;
+        }
+        _ => {
+            bot.answer_callback_query(callback_id)
+                .text(texts.unknown_button)
+                .show_alert(true)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Pairs of item indexes in the order they are shown. Neighbouring items go
+/// first and the same item rarely appears in two pairs in a row.
+fn pairs(items_count: usize) -> Vec<(usize, usize)> {
+    let mut pairs: Vec<(usize, usize)> = (0..items_count)
+        .flat_map(|left| (left + 1..items_count).map(move |right| (left, right)))
+        .collect();
+
+    pairs.sort_by_key(|(left, right)| (right - left, *left));
+
+    pairs
+}
+
+/// Elo ratings of the items, replaying the answers pair by pair.
+fn rate(items_count: usize, answers: &[ComparisonAnswer]) -> Vec<f64> {
+    let pairs = pairs(items_count);
+    let mut ratings = vec![INITIAL_RATING; items_count];
+
+    for answer in answers {
+        let Some(&(left, right)) = pairs.get(answer.pair) else {
+            continue;
+        };
+
+        let score = match answer.outcome.as_str() {
+            "left" => 1.0,
+            "right" => 0.0,
+            _ => 0.5,
+        };
+        let expected = 1.0 / (1.0 + 10f64.powf((ratings[right] - ratings[left]) / 400.0));
+        let delta = ELO_K * (score - expected);
+
+        ratings[left] += delta;
+        ratings[right] -= delta;
+    }
+
+    ratings
+}
+
+/// Spreads the ratings over as many deck cards as there are items, the
+/// biggest item getting the biggest card.
+fn suggest_bucket(rating: f64, min: f64, max: f64, buckets: &[&'static str]) -> &'static str {
+    let normalized = if max > min {
+        (rating - min) / (max - min)
+    } else {
+        0.5
+    };
+
+    buckets[(normalized * (buckets.len() - 1) as f64).round() as usize]
+}
+
+fn render_pair(items: &[String], pair: usize, answers_count: usize, texts: &Texts) -> String {
+    let pairs = pairs(items.len());
+    let (left, right) = pairs[pair];
+
+    (texts.compare_pair)(
+        pair + 1,
+        pairs.len(),
+        &items[left],
+        &items[right],
+        answers_count,
+    )
+}
+
+fn render_ranking(
+    comparison: &Comparison,
+    answers: &[ComparisonAnswer],
+    deck: &[&'static str],
+    texts: &Texts,
+) -> String {
+    if answers.is_empty() {
+        return texts.comparison_without_answers.to_string();
+    }
+
+    let ratings = rate(comparison.items.len(), answers);
+    let min = ratings.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = ratings.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    let cards: Vec<&'static str> = deck
+        .iter()
+        .filter(|card| **card != "0")
+        .take(comparison.items.len())
+        .copied()
+        .collect();
+
+    let mut ranking: Vec<(usize, f64)> = ratings.iter().copied().enumerate().collect();
+    ranking.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    let lines: Vec<String> = ranking
+        .iter()
+        .enumerate()
+        .map(|(place, (item, rating))| {
+            (texts.ranking_line)(
+                place + 1,
+                &comparison.items[*item],
+                suggest_bucket(*rating, min, max, &cards),
+                *rating,
+            )
+        })
+        .collect();
+
+    format!("{}\n\n{}", texts.ranking_heading, lines.join("\n"))
+}
+
+/// Splits `cmp_<action>:<pair>` callback data.
+fn parse_data(data: &str) -> Option<(&str, usize)> {
+    let (action, pair) = data.split_once(':')?;
+
+    Some((action, pair.parse().ok()?))
+}
+
+fn make_keyboard(pair: usize, texts: &Texts) -> InlineKeyboardMarkup {
+    let make_row = |labels: &[&str], data: &[&str]| -> Vec<InlineKeyboardButton> {
+        labels
+            .iter()
+            .zip(data)
+            .map(|(label, data)| {
+                InlineKeyboardButton::callback(label.to_owned(), format!("{}:{}", data, pair))
+            })
+            .collect()
+    };
+
+    InlineKeyboardMarkup::new(vec![
+        make_row(&texts.compare_answer_buttons, &COMPARE_ANSWER_BUTTONS),
+        make_row(&texts.compare_func_buttons, &COMPARE_FUNC_BUTTONS),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(pair: usize, outcome: &str) -> ComparisonAnswer {
+        ComparisonAnswer {
+            pair,
+            outcome: outcome.to_string(),
+        }
+    }
+
+    #[test]
+    fn pairs_go_from_neighbours_to_the_farthest_items() {
+        assert_eq!(pairs(2), vec![(0, 1)]);
+        assert_eq!(
+            pairs(4),
+            vec![(0, 1), (1, 2), (2, 3), (0, 2), (1, 3), (0, 3)]
+        );
+    }
+
+    #[test]
+    fn unanimous_winner_ranks_first() {
+        // Pairs of three items: (0, 1), (1, 2), (0, 2).
+        let answers = [
+            answer(0, "right"),
+            answer(0, "right"),
+            answer(1, "left"),
+            answer(1, "left"),
+            answer(2, "right"),
+            answer(2, "right"),
+        ];
+        let ratings = rate(3, &answers);
+
+        assert!(ratings[1] > ratings[2]);
+        assert!(ratings[1] > ratings[0]);
+        assert!(ratings[2] > ratings[0]);
+    }
+
+    #[test]
+    fn same_answers_and_unknown_pairs_keep_ratings() {
+        let ratings = rate(2, &[answer(0, "same"), answer(5, "left")]);
+
+        assert_eq!(ratings, vec![INITIAL_RATING, INITIAL_RATING]);
+    }
+
+    #[test]
+    fn buckets_follow_the_rating() {
+        let buckets = ["1", "2", "3", "5", "8"];
+
+        assert_eq!(suggest_bucket(900.0, 900.0, 1100.0, &buckets), "1");
+        assert_eq!(suggest_bucket(1000.0, 900.0, 1100.0, &buckets), "3");
+        assert_eq!(suggest_bucket(1100.0, 900.0, 1100.0, &buckets), "8");
+    }
+
+    #[test]
+    fn equal_ratings_map_to_the_middle_bucket() {
+        let buckets = ["1", "2", "3", "5", "8"];
+
+        assert_eq!(suggest_bucket(1000.0, 1000.0, 1000.0, &buckets), "3");
+    }
+
+    #[test]
+    fn parses_pair_of_the_button() {
+        assert_eq!(parse_data("cmp_left:3"), Some(("cmp_left", 3)));
+        assert_eq!(parse_data("cmp_next:0"), Some(("cmp_next", 0)));
+        assert_eq!(parse_data("cmp_left"), None);
+        assert_eq!(parse_data("cmp_left:x"), None);
+    }
+}
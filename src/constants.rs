@@ -1,13 +1,24 @@
-// pub const DEFAULT_SEQ: [&'static str; 11] =
+// pub const DEFAULT_SEQ: [&str; 11] =
 //     ["0", "0.5", "1", "2", "3", "5", "8", "13", "20", "40", "100"];
-pub const DEFAULT_SEQ: [&'static str; 11] =
-    ["0", "1", "2", "3", "5", "8", "13", "21", "34", "55", "89"];
-pub const FUNC_BUTTONS: [(&'static str, &'static str); 2] =
-    [("Перезапустить", "restart"), ("Завершить", "finish")];
+pub const DEFAULT_SEQ: [&str; 11] = ["0", "1", "2", "3", "5", "8", "13", "21", "34", "55", "89"];
+pub const MODIFIED_FIBONACCI_SEQ: [&str; 10] =
+    ["0", "1", "2", "3", "5", "8", "13", "20", "40", "100"];
+pub const POWERS_OF_TWO_SEQ: [&str; 8] = ["0", "1", "2", "4", "8", "16", "32", "64"];
+/// Emoji of the confidence buttons in the order of `Confidence::ALL`, the
+/// labels come from the texts of the session's language.
+pub const CONFIDENCE_EMOJI: [&str; 3] = ["😟", "🙂", "😎"];
+/// Timers of the settings menu and the `/poker` dialogue, in minutes.
+pub const TIMER_OPTIONS: [Option<usize>; 6] =
+    [None, Some(2), Some(5), Some(10), Some(15), Some(30)];
+/// Commands only shown to chat administrators in the "/" menu of groups.
+pub const ADMIN_COMMANDS: [&str; 2] = ["config", "settings"];
 
-pub const URL_REGEX: &'static str =
+pub const COMPARE_ANSWER_BUTTONS: [&str; 3] = ["cmp_left", "cmp_same", "cmp_right"];
+pub const COMPARE_FUNC_BUTTONS: [&str; 2] = ["cmp_next", "cmp_finish"];
+
+pub const URL_REGEX: &str =
     r"https?://(www\.)?[-a-zA-Z0-9@:%._\+~#=]{2,256}\.[a-z]{2,4}\b([-a-zA-Z0-9@:%_\+.~#?&//=]*)";
 
-pub const ISSUE_ID_REGEX: &'static str = r"[A-Z]+-\d+";
+pub const ISSUE_ID_REGEX: &str = r"[A-Z]+-\d+";
 
-pub const EMOJI_SET: [&'static str; 4] = ["♦️", "♠️", "♣️", "♥️"];
+pub const EMOJI_SET: [&str; 4] = ["♦️", "♠️", "♣️", "♥️"];
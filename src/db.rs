@@ -1,27 +1,676 @@
-use rusqlite::{params, Connection, Error};
-use teloxide::types::{ChatId, MessageId, UserId};
+use crate::constants::{DEFAULT_SEQ, MODIFIED_FIBONACCI_SEQ, POWERS_OF_TWO_SEQ};
+use rusqlite::{params, Connection, Error, OptionalExtension, Transaction, TransactionBehavior};
+use std::str::FromStr;
+use teloxide::types::{ChatId, MessageId, User, UserId};
 
-#[derive(Debug)]
+/// Schema changes applied on top of the base tables, in order. The index of
+/// the last applied entry is kept in `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    "
+    ALTER TABLE sessions ADD COLUMN vote_mode VARCHAR(16) DEFAULT 'single' NOT NULL;
+    ALTER TABLE estimations ADD COLUMN value_max INTEGER;
+    ALTER TABLE chat_configs ADD COLUMN vote_mode VARCHAR(16) DEFAULT 'single' NOT NULL;
+
+    -- The foreign key of chat_id pointed at a column sessions does not have,
+    -- which made every write to chat_configs fail.
+    CREATE TABLE chat_configs_new (
+        id INTEGER PRIMARY KEY,
+        tg_chat_id INT NOT NULL,
+        seq VARCHAR(255),
+        chat_id INT NOT NULL,
+        vote_mode VARCHAR(16) DEFAULT 'single' NOT NULL,
+        UNIQUE(tg_chat_id)
+    );
+
+    INSERT INTO chat_configs_new (id, tg_chat_id, seq, chat_id, vote_mode)
+    SELECT id, tg_chat_id, seq, chat_id, vote_mode
+    FROM chat_configs;
+
+    DROP TABLE chat_configs;
+    ALTER TABLE chat_configs_new RENAME TO chat_configs;
+",
+    "
+    ALTER TABLE estimations ADD COLUMN confidence VARCHAR(8);
+",
+    "
+    CREATE TABLE comparisons (
+        id INTEGER PRIMARY KEY,
+        tg_chat_id INTEGER NOT NULL,
+        tg_message_id INTEGER NOT NULL,
+        current_pair INT DEFAULT 0 NOT NULL,
+        finished TINYINT DEFAULT 0 NOT NULL,
+        initiator_id INT NOT NULL,
+        FOREIGN KEY(initiator_id) REFERENCES users(id),
+        UNIQUE(tg_chat_id, tg_message_id)
+    );
+
+    CREATE TABLE comparison_items (
+        id INTEGER PRIMARY KEY,
+        comparison_id INT NOT NULL,
+        position INT NOT NULL,
+        title VARCHAR(255) NOT NULL,
+        FOREIGN KEY(comparison_id) REFERENCES comparisons(id),
+        UNIQUE(comparison_id, position)
+    );
+
+    CREATE TABLE comparison_answers (
+        id INTEGER PRIMARY KEY,
+        comparison_id INT NOT NULL,
+        pair INT NOT NULL,
+        outcome VARCHAR(8) NOT NULL,
+        user_id INT NOT NULL,
+        FOREIGN KEY(comparison_id) REFERENCES comparisons(id),
+        FOREIGN KEY(user_id) REFERENCES users(id),
+        UNIQUE(comparison_id, pair, user_id)
+    );
+",
+    "
+    ALTER TABLE chat_configs ADD COLUMN max_spread INT;
+",
+    "
+    ALTER TABLE chat_configs ADD COLUMN max_estimate INT;
+    ALTER TABLE chat_configs ADD COLUMN min_voters INT;
+    ALTER TABLE chat_configs ADD COLUMN outlier_comments TINYINT DEFAULT 0 NOT NULL;
+    ALTER TABLE estimations ADD COLUMN comment VARCHAR(255);
+",
+    "
+    ALTER TABLE sessions ADD COLUMN created_at INTEGER;
+    ALTER TABLE sessions ADD COLUMN finished_at INTEGER;
+",
+    "
+    ALTER TABLE sessions ADD COLUMN round INT DEFAULT 1 NOT NULL;
+
+    CREATE TABLE past_estimations (
+        id INTEGER PRIMARY KEY,
+        round INT NOT NULL,
+        value INTEGER,
+        value_max INTEGER,
+        confidence VARCHAR(8),
+        comment VARCHAR(255),
+        session_id INT NOT NULL,
+        user_id INT NOT NULL,
+        FOREIGN KEY(session_id) REFERENCES sessions(id),
+        FOREIGN KEY(user_id) REFERENCES users(id)
+    );
+",
+    "
+    CREATE TABLE queued_sessions (
+        id INTEGER PRIMARY KEY,
+        tg_chat_id INTEGER NOT NULL,
+        title VARCHAR(255),
+        description VARCHAR(1000),
+        initiator_id INT NOT NULL,
+        FOREIGN KEY(initiator_id) REFERENCES users(id)
+    );
+",
+    "
+    ALTER TABLE chat_configs ADD COLUMN language VARCHAR(8);
+    ALTER TABLE chat_configs ADD COLUMN reveal_mode VARCHAR(16) DEFAULT 'hidden' NOT NULL;
+    ALTER TABLE chat_configs ADD COLUMN auto_reveal INT;
+    ALTER TABLE chat_configs ADD COLUMN permissions VARCHAR(16) DEFAULT 'initiator' NOT NULL;
+    ALTER TABLE chat_configs ADD COLUMN timer_minutes INT;
+    ALTER TABLE chat_configs ADD COLUMN anonymous TINYINT DEFAULT 0 NOT NULL;
+    ALTER TABLE sessions ADD COLUMN deck VARCHAR(16) DEFAULT 'fibonacci' NOT NULL;
+    ALTER TABLE sessions ADD COLUMN anonymous TINYINT DEFAULT 0 NOT NULL;
+    ALTER TABLE sessions ADD COLUMN timer_minutes INT;
+    ALTER TABLE sessions ADD COLUMN deadline INTEGER;
+",
+    "
+    ALTER TABLE sessions ADD COLUMN language VARCHAR(8) DEFAULT 'ru' NOT NULL;
+    ALTER TABLE comparisons ADD COLUMN language VARCHAR(8) DEFAULT 'ru' NOT NULL;
+",
+    "
+    CREATE TABLE sessions_new (
+        id INTEGER PRIMARY KEY,
+        tg_chat_id INTEGER,
+        tg_message_id INTEGER,
+        inline_message_id VARCHAR(255),
+        title VARCHAR(255),
+        description VARCHAR(1000),
+        finished TINYINT DEFAULT 0 NOT NULL,
+        initiator_id INT NOT NULL,
+        vote_mode VARCHAR(16) DEFAULT 'single' NOT NULL,
+        created_at INTEGER,
+        finished_at INTEGER,
+        round INT DEFAULT 1 NOT NULL,
+        deck VARCHAR(16) DEFAULT 'fibonacci' NOT NULL,
+        anonymous TINYINT DEFAULT 0 NOT NULL,
+        timer_minutes INT,
+        deadline INTEGER,
+        language VARCHAR(8) DEFAULT 'ru' NOT NULL,
+        FOREIGN KEY(initiator_id) REFERENCES users(id),
+        UNIQUE(tg_chat_id, tg_message_id),
+        UNIQUE(inline_message_id)
+    );
+
+    INSERT INTO sessions_new (id, tg_chat_id, tg_message_id, title, description, finished, initiator_id, vote_mode, created_at, finished_at, round, deck, anonymous, timer_minutes, deadline, language)
+    SELECT id, tg_chat_id, tg_message_id, title, description, finished, initiator_id, vote_mode, created_at, finished_at, round, deck, anonymous, timer_minutes, deadline, language
+    FROM sessions;
+
+    DROP TABLE sessions;
+    ALTER TABLE sessions_new RENAME TO sessions;
+",
+    "
+    ALTER TABLE sessions ADD COLUMN tg_thread_id INTEGER;
+",
+    "
+    ALTER TABLE sessions ADD COLUMN state VARCHAR(16) DEFAULT 'open' NOT NULL;
+    UPDATE sessions SET state = CASE finished WHEN 1 THEN 'revealed' WHEN 2 THEN 'cancelled' ELSE 'open' END;
+    ALTER TABLE sessions DROP COLUMN finished;
+",
+    "
+    ALTER TABLE sessions ADD COLUMN tg_command_message_id INTEGER;
+",
+    "
+    CREATE TABLE dialogues (
+        tg_chat_id INTEGER NOT NULL,
+        tg_user_id INTEGER NOT NULL,
+        step VARCHAR(16) NOT NULL,
+        tg_prompt_message_id INTEGER NOT NULL,
+        tg_thread_id INTEGER,
+        title VARCHAR(255) DEFAULT '' NOT NULL,
+        description VARCHAR(1000) DEFAULT '' NOT NULL,
+        deck VARCHAR(16),
+        language VARCHAR(8) NOT NULL,
+        PRIMARY KEY(tg_chat_id, tg_user_id)
+    );
+",
+    "
+    ALTER TABLE sessions ADD COLUMN description_markdown TEXT;
+",
+    "
+    -- The deck was kept in the seq column, created by the first version unused.
+    ALTER TABLE chat_configs RENAME COLUMN seq TO deck;
+",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VoteMode {
+    #[default]
+    Single,
+    /// The first card press sets the lower bound, the second one the upper.
+    Range,
+}
+
+impl VoteMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoteMode::Single => "single",
+            VoteMode::Range => "range",
+        }
+    }
+}
+
+impl FromStr for VoteMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single" => Ok(VoteMode::Single),
+            "range" => Ok(VoteMode::Range),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Lifecycle of a session. Votes are taken while it is open and shown once it
+/// is revealed, a new round brings a revealed session back to open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    Open,
+    Revealed,
+    /// The team moved on to the next queued task, the estimate is settled.
+    Finalized,
+    /// Aborted, the votes do not count whether they were shown or not.
+    Cancelled,
+    /// The session message is gone, the session is no longer listed.
+    Archived,
+}
+
+impl SessionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionState::Open => "open",
+            SessionState::Revealed => "revealed",
+            SessionState::Finalized => "finalized",
+            SessionState::Cancelled => "cancelled",
+            SessionState::Archived => "archived",
+        }
+    }
+
+    /// Whether the session may move to `next`. An open session may also
+    /// start its round over.
+    pub fn can_become(&self, next: SessionState) -> bool {
+        use SessionState::*;
+
+        matches!(
+            (self, next),
+            (Open, Open | Revealed | Cancelled)
+                | (Revealed, Open | Finalized | Cancelled)
+                | (Open | Revealed | Finalized | Cancelled, Archived)
+        )
+    }
+
+    /// The votes of the last round are shown.
+    pub fn is_revealed(&self) -> bool {
+        matches!(self, SessionState::Revealed | SessionState::Finalized)
+    }
+}
+
+impl FromStr for SessionState {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(SessionState::Open),
+            "revealed" => Ok(SessionState::Revealed),
+            "finalized" => Ok(SessionState::Finalized),
+            "cancelled" => Ok(SessionState::Cancelled),
+            "archived" => Ok(SessionState::Archived),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Confidence {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Confidence {
+    pub const ALL: [Confidence; 3] = [Confidence::Low, Confidence::Medium, Confidence::High];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+
+    /// Weight of a vote in the confidence-weighted average.
+    pub fn weight(&self) -> f64 {
+        match self {
+            Confidence::Low => 1.0,
+            Confidence::Medium => 2.0,
+            Confidence::High => 3.0,
+        }
+    }
+}
+
+impl FromStr for Confidence {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Confidence::Low),
+            "medium" => Ok(Confidence::Medium),
+            "high" => Ok(Confidence::High),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Deck {
+    #[default]
+    Fibonacci,
+    /// Fibonacci rounded to 20, 40 and 100 for the big numbers.
+    Modified,
+    PowersOfTwo,
+}
+
+impl Deck {
+    pub const ALL: [Deck; 3] = [Deck::Fibonacci, Deck::Modified, Deck::PowersOfTwo];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Deck::Fibonacci => "fibonacci",
+            Deck::Modified => "modified",
+            Deck::PowersOfTwo => "powers",
+        }
+    }
+
+    pub fn cards(&self) -> &'static [&'static str] {
+        match self {
+            Deck::Fibonacci => &DEFAULT_SEQ,
+            Deck::Modified => &MODIFIED_FIBONACCI_SEQ,
+            Deck::PowersOfTwo => &POWERS_OF_TWO_SEQ,
+        }
+    }
+}
+
+impl FromStr for Deck {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fibonacci" => Ok(Deck::Fibonacci),
+            "modified" => Ok(Deck::Modified),
+            "powers" => Ok(Deck::PowersOfTwo),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Ru,
+    En,
+}
+
+impl Language {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Ru => "ru",
+            Language::En => "en",
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ru" => Ok(Language::Ru),
+            "en" => Ok(Language::En),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RevealMode {
+    /// Votes are shown as suits until the session is finished.
+    #[default]
+    Hidden,
+    /// Votes are shown as soon as they are cast.
+    Open,
+}
+
+impl RevealMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevealMode::Hidden => "hidden",
+            RevealMode::Open => "open",
+        }
+    }
+}
+
+impl FromStr for RevealMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hidden" => Ok(RevealMode::Hidden),
+            "open" => Ok(RevealMode::Open),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Who may finish, restart and switch sessions besides voting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Permissions {
+    #[default]
+    Initiator,
+    InitiatorAndAdmins,
+    Everyone,
+}
+
+impl Permissions {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permissions::Initiator => "initiator",
+            Permissions::InitiatorAndAdmins => "admins",
+            Permissions::Everyone => "everyone",
+        }
+    }
+}
+
+impl FromStr for Permissions {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "initiator" => Ok(Permissions::Initiator),
+            "admins" => Ok(Permissions::InitiatorAndAdmins),
+            "everyone" => Ok(Permissions::Everyone),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Where the session message lives. Messages posted through inline mode
+/// belong to a chat the bot does not know, only their own id is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionMessage {
+    Chat(ChatId, MessageId),
+    Inline(String),
+}
+
+#[derive(Debug, Clone)]
 pub struct SessionWithInitiator {
     pub session_id: usize,
     pub title: String,
     pub description: String,
-    pub finished: u8,
+    /// The description as MarkdownV2 with the formatting of the `/poker`
+    /// message, built from its entities. `None` shows the plain description.
+    pub description_markdown: Option<String>,
+    pub state: SessionState,
+    pub vote_mode: VoteMode,
+    pub deck: Deck,
+    /// Names are hidden next to the revealed votes.
+    pub anonymous: bool,
+    /// The session finishes by itself this many minutes after a round starts.
+    pub timer_minutes: Option<usize>,
+    /// Language of the session message, picked when the session starts.
+    pub language: Language,
     pub initiator_first_name: String,
     pub initiator_last_name: String,
     pub initiator_username: String,
     pub initiator_db_id: usize,
+    pub message: SessionMessage,
+    /// Forum topic of the session message, `None` outside of topics.
+    pub thread_id: Option<i32>,
 }
 
-#[derive(Debug)]
+impl SessionWithInitiator {
+    /// `None` for sessions started through inline mode.
+    pub fn chat_id(&self) -> Option<ChatId> {
+        match self.message {
+            SessionMessage::Chat(chat_id, _) => Some(chat_id),
+            SessionMessage::Inline(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct EstimationWithUser {
     pub id: usize,
     pub value: usize,
+    /// Upper bound of a range vote, `None` for single votes and for range votes
+    /// still waiting for the second card.
+    pub value_max: Option<usize>,
+    pub confidence: Option<Confidence>,
+    pub comment: Option<String>,
+    pub first_name: String,
+    pub last_name: String,
+    pub username: String,
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    pub id: usize,
+    pub current_pair: usize,
+    pub finished: u8,
+    pub initiator_db_id: usize,
+    pub language: Language,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ComparisonAnswer {
+    pub pair: usize,
+    pub outcome: String,
+}
+
+#[derive(Debug)]
+pub struct SessionSummary {
+    pub session_id: usize,
+    pub tg_message_id: i32,
+    pub title: String,
+    /// Creation date as `dd.mm.yyyy`, missing for sessions created before
+    /// dates were recorded.
+    pub created: Option<String>,
+    pub state: SessionState,
+    /// Midpoints of the votes, so range votes count as a single value.
+    pub votes: Vec<f64>,
+}
+
+#[derive(Debug)]
+pub struct VoterActivity {
+    pub user_db_id: usize,
+    pub first_name: String,
+    pub last_name: String,
+    pub username: String,
+    pub sessions_voted: usize,
+}
+
+#[derive(Debug)]
+pub struct SessionVote {
+    pub session_id: usize,
+    pub user_db_id: usize,
+    pub value: usize,
+    pub value_max: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ExportSession {
+    pub session_id: usize,
+    pub title: String,
+    pub description: String,
+    pub state: SessionState,
+    pub vote_mode: VoteMode,
+    pub round: usize,
+    pub anonymous: bool,
+    pub initiator_tg_id: u64,
+    pub initiator_first_name: String,
+    pub initiator_last_name: String,
+    pub initiator_username: String,
+    /// ISO 8601 timestamps in UTC.
+    pub created_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// Votes of all revealed rounds, ordered by round.
+    pub votes: Vec<ExportVote>,
+}
+
+#[derive(Debug)]
+pub struct ExportVote {
+    pub round: usize,
+    pub voter_tg_id: u64,
+    pub first_name: String,
+    pub last_name: String,
+    pub username: String,
+    pub value: usize,
+    pub value_max: Option<usize>,
+    pub confidence: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DbUser {
     pub first_name: String,
     pub last_name: String,
     pub username: String,
 }
 
+/// A task imported into the chat's queue, waiting for its session.
+#[derive(Debug)]
+pub struct QueuedSession {
+    pub title: String,
+    pub description: String,
+    pub initiator_db_id: usize,
+}
+
+/// Question of the `/poker` dialogue waiting for an answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogueStep {
+    Title,
+    Description,
+    Deck,
+    Timer,
+}
+
+impl DialogueStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DialogueStep::Title => "title",
+            DialogueStep::Description => "description",
+            DialogueStep::Deck => "deck",
+            DialogueStep::Timer => "timer",
+        }
+    }
+}
+
+impl FromStr for DialogueStep {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(DialogueStep::Title),
+            "description" => Ok(DialogueStep::Description),
+            "deck" => Ok(DialogueStep::Deck),
+            "timer" => Ok(DialogueStep::Timer),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A session being set up through the `/poker` dialogue, one per user and
+/// chat.
+#[derive(Debug, Clone)]
+pub struct Dialogue {
+    pub chat_id: ChatId,
+    pub user_id: UserId,
+    pub step: DialogueStep,
+    /// The message asking the current question, answers reply to it.
+    pub prompt_message_id: MessageId,
+    pub thread_id: Option<i32>,
+    pub title: String,
+    pub description: String,
+    /// `None` until picked.
+    pub deck: Option<Deck>,
+    pub language: Language,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ChatConfig {
+    pub vote_mode: VoteMode,
+    /// Allowed distance in cards between the highest and the lowest vote.
+    pub max_spread: Option<usize>,
+    /// Estimates above this value should be split into smaller stories.
+    pub max_estimate: Option<usize>,
+    /// Minimum number of voters for the result to count.
+    pub min_voters: Option<usize>,
+    /// Whether the voters with the lowest and the highest estimates have to
+    /// explain them.
+    pub outlier_comments: bool,
+    pub deck: Deck,
+    /// `None` picks the language of the user who started the session.
+    pub language: Option<Language>,
+    pub reveal_mode: RevealMode,
+    /// Number of votes after which the session finishes by itself.
+    pub auto_reveal: Option<usize>,
+    pub permissions: Permissions,
+    pub timer_minutes: Option<usize>,
+    pub anonymous: bool,
+}
+
 pub fn prepare_database(conn: &mut Connection) -> Result<(), Error> {
     conn.execute_batch(
         "
@@ -52,7 +701,6 @@ pub fn prepare_database(conn: &mut Connection) -> Result<(), Error> {
                 tg_chat_id INT NOT NULL,
                 seq VARCHAR(255),
                 chat_id INT NOT NULL,
-                FOREIGN KEY(chat_id) REFERENCES sessions(chat_id),
                 UNIQUE(tg_chat_id)
             );
 
@@ -64,36 +712,82 @@ pub fn prepare_database(conn: &mut Connection) -> Result<(), Error> {
                 username VARCHAR(32),
                 UNIQUE(tg_id)
             );
-        
         ",
     )?;
 
-    Ok::<_, Error>(())
+    migrate(conn)
 }
 
-pub fn save_session(
-    conn: &mut Connection,
-    tg_chat_id: ChatId,
-    tg_message_id: i32,
-    title: String,
-    description: String,
-    initiator_id: String,
-) -> Result<usize, Error> {
+/// Foreign keys are off while migrating, so tables can be rebuilt with the
+/// rows referencing them in place. The pragma has no effect inside a
+/// transaction.
+fn migrate(conn: &mut Connection) -> Result<(), Error> {
+    let version: usize = conn.query_row("PRAGMA user_version;", [], |row| row.get(0))?;
+
+    conn.pragma_update(None, "foreign_keys", false)?;
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", idx + 1)?;
+        tx.commit()?;
+    }
+
+    conn.pragma_update(None, "foreign_keys", true)
+}
+
+fn enum_from_row<T: FromStr>(row: &rusqlite::Row, idx: usize) -> Result<T, Error> {
+    let raw: String = row.get(idx)?;
+
+    raw.parse()
+        .map_err(|_| Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, raw.into()))
+}
+
+/// Id of the saved session.
+pub fn save_session(conn: &mut Connection, session: SessionWithInitiator) -> Result<usize, Error> {
+    let (tg_chat_id, tg_message_id, inline_message_id) = match session.message {
+        SessionMessage::Chat(chat_id, message_id) => (Some(chat_id.0), Some(message_id.0), None),
+        SessionMessage::Inline(inline_message_id) => (None, None, Some(inline_message_id)),
+    };
+
     conn.execute(
         "
-        INSERT INTO sessions (tg_chat_id, tg_message_id, title, description, initiator_id) VALUES (?1, ?2, ?3, ?4, ?5); 
+        INSERT INTO sessions (tg_chat_id, tg_message_id, title, description, initiator_id, vote_mode, deck, anonymous, timer_minutes, language, inline_message_id, tg_thread_id, description_markdown, deadline, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, unixepoch() + ?9 * 60, unixepoch());
     ",
-        [
-            tg_chat_id.to_string(),
-            tg_message_id.to_string(),
-            title,
-            description,
-            initiator_id,
+        params![
+            tg_chat_id,
+            tg_message_id,
+            session.title,
+            session.description,
+            session.initiator_db_id,
+            session.vote_mode.as_str(),
+            session.deck.as_str(),
+            session.anonymous,
+            session.timer_minutes,
+            session.language.as_str(),
+            inline_message_id,
+            session.thread_id,
+            session.description_markdown,
         ],
+    )?;
+
+    Ok(conn.last_insert_rowid() as usize)
+}
+
+/// Saves the current names of a Telegram user, returning its id in the
+/// database.
+pub fn upsert_user(conn: &mut Connection, user: &User) -> Result<usize, Error> {
+    find_or_insert_user(
+        conn,
+        user.id,
+        user.first_name.clone(),
+        user.last_name.clone().unwrap_or_default(),
+        user.username.clone().unwrap_or_default(),
     )
 }
 
-pub fn find_or_insert_user(
+fn find_or_insert_user(
     conn: &mut Connection,
     user_id: UserId,
     first_name: String,
@@ -128,71 +822,289 @@ pub fn find_or_insert_user(
     }
 }
 
-pub fn find_session_with_initiator(
+const SESSION_WITH_INITIATOR_QUERY: &str = "
+    SELECT sessions.id as session_id, state, title, description, users.first_name as initiator_first_name, users.last_name as initiator_last_name, users.username as initiator_username, users.id, vote_mode, tg_chat_id, tg_message_id, deck, anonymous, timer_minutes, language, inline_message_id, tg_thread_id, description_markdown
+    FROM sessions
+    JOIN users ON sessions.initiator_id = users.id";
+
+fn session_with_initiator_from_row(row: &rusqlite::Row) -> Result<SessionWithInitiator, Error> {
+    Ok(SessionWithInitiator {
+        session_id: row.get(0)?,
+        state: enum_from_row(row, 1)?,
+        title: row.get(2)?,
+        description: row.get(3)?,
+        initiator_first_name: row.get(4)?,
+        initiator_last_name: row.get(5)?,
+        initiator_username: row.get(6)?,
+        initiator_db_id: row.get(7)?,
+        vote_mode: enum_from_row(row, 8)?,
+        message: match row.get::<usize, Option<String>>(15)? {
+            Some(inline_message_id) => SessionMessage::Inline(inline_message_id),
+            None => SessionMessage::Chat(ChatId(row.get(9)?), MessageId(row.get(10)?)),
+        },
+        thread_id: row.get(16)?,
+        description_markdown: row.get(17)?,
+        deck: enum_from_row(row, 11)?,
+        anonymous: row.get(12)?,
+        timer_minutes: row.get(13)?,
+        language: enum_from_row(row, 14)?,
+    })
+}
+
+pub fn find_user(conn: &mut Connection, user_id: usize) -> Result<DbUser, Error> {
+    let query = "SELECT first_name, last_name, username FROM users WHERE id = ?1;";
+
+    conn.query_row(query, [user_id], |row| {
+        Ok(DbUser {
+            first_name: row.get(0)?,
+            last_name: row.get::<usize, Option<String>>(1)?.unwrap_or_default(),
+            username: row.get::<usize, Option<String>>(2)?.unwrap_or_default(),
+        })
+    })
+}
+
+fn find_session_with_initiator(
     conn: &mut Connection,
     chat_id: ChatId,
     message_id: MessageId,
 ) -> Result<SessionWithInitiator, Error> {
-    let query = "
-        SELECT sessions.id as session_id, finished, title, description, users.first_name as initiator_first_name, users.last_name as initiator_last_name, users.username as initiator_username, users.id
-        FROM sessions
-        JOIN users ON sessions.initiator_id = users.id
-        WHERE sessions.tg_chat_id = ?1 AND sessions.tg_message_id = ?2;";
+    let query = format!(
+        "{} WHERE sessions.tg_chat_id = ?1 AND sessions.tg_message_id = ?2;",
+        SESSION_WITH_INITIATOR_QUERY
+    );
 
     conn.query_row(
-        query,
+        &query,
         params![chat_id.to_string(), message_id.to_string()],
-        |row| {
-            Ok(SessionWithInitiator {
-                session_id: row.get(0)?,
-                finished: row.get(1)?,
-                title: row.get(2)?,
-                description: row.get(3)?,
-                initiator_first_name: row.get(4)?,
-                initiator_last_name: row.get(5)?,
-                initiator_username: row.get(6)?,
-                initiator_db_id: row.get(7)?,
-            })
-        },
+        session_with_initiator_from_row,
     )
 }
 
-pub fn insert_update_estimation(
+/// `None` when no session is bound to the message.
+pub fn find_session_by_message(
     conn: &mut Connection,
-    user_id: usize,
-    session_id: usize,
-    value: String,
-) -> Result<usize, Error> {
-    let select_query = "
-        SELECT COUNT(*)
-        FROM estimations
-        WHERE session_id = ?1 AND user_id = ?2;
-    ";
-    let select_result = conn.query_row(
-        select_query,
-        [session_id.to_string(), user_id.to_string()],
-        |row| row.get(0),
-    );
-
-    let query = "
-        INSERT INTO estimations (value, session_id, user_id)
-        VALUES (?1, ?2, ?3)
-        ON CONFLICT(user_id, session_id)
-        DO UPDATE SET value = ?1
-        WHERE session_id = ?2 AND user_id = ?3;
-    ";
+    message: SessionMessage,
+) -> Result<Option<SessionWithInitiator>, Error> {
+    match message {
+        SessionMessage::Chat(chat_id, message_id) => {
+            find_session_with_initiator(conn, chat_id, message_id)
+        }
+        SessionMessage::Inline(inline_message_id) => find_inline_session(conn, inline_message_id),
+    }
+    .optional()
+}
 
-    let _ = conn.execute(query, [value, session_id.to_string(), user_id.to_string()]);
+fn find_inline_session(
+    conn: &mut Connection,
+    inline_message_id: String,
+) -> Result<SessionWithInitiator, Error> {
+    let query = format!(
+        "{} WHERE sessions.inline_message_id = ?1;",
+        SESSION_WITH_INITIATOR_QUERY
+    );
 
-    select_result
+    conn.query_row(&query, [inline_message_id], session_with_initiator_from_row)
 }
 
-pub fn find_estimations(
+pub fn find_session_by_id(
     conn: &mut Connection,
     session_id: usize,
-) -> Result<Vec<EstimationWithUser>, Error> {
-    let query = "
-        SELECT estimations.id, value, users.first_name, users.last_name, users.username FROM estimations
+) -> Result<SessionWithInitiator, Error> {
+    let query = format!("{} WHERE sessions.id = ?1;", SESSION_WITH_INITIATOR_QUERY);
+
+    conn.query_row(&query, [session_id], session_with_initiator_from_row)
+}
+
+/// Open sessions whose timer has run out, in all chats.
+pub fn find_expired_sessions(conn: &mut Connection) -> Result<Vec<SessionWithInitiator>, Error> {
+    let query = format!(
+        "{} WHERE state = 'open' AND deadline <= unixepoch() ORDER BY sessions.id;",
+        SESSION_WITH_INITIATOR_QUERY
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map([], session_with_initiator_from_row)?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?)
+    }
+
+    Ok(result)
+}
+
+/// Sessions of the chat still open for voting, oldest first. With a topic
+/// given, only the sessions of that topic.
+pub fn find_active_sessions(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    tg_thread_id: Option<i32>,
+) -> Result<Vec<SessionWithInitiator>, Error> {
+    let query = format!(
+        "{} WHERE sessions.tg_chat_id = ?1 AND (?2 IS NULL OR tg_thread_id = ?2) AND state = 'open' ORDER BY sessions.id;",
+        SESSION_WITH_INITIATOR_QUERY
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(
+        params![tg_chat_id.to_string(), tg_thread_id],
+        session_with_initiator_from_row,
+    )?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?)
+    }
+
+    Ok(result)
+}
+
+/// Whether the session takes votes, checked inside the transaction that
+/// records the vote so a concurrent reveal cannot slip in between.
+fn session_state(tx: &Transaction, session_id: usize) -> Result<SessionState, Error> {
+    tx.query_row(
+        "SELECT state FROM sessions WHERE id = ?1;",
+        [session_id],
+        |row| enum_from_row(row, 0),
+    )
+}
+
+fn is_session_open(tx: &Transaction, session_id: usize) -> Result<bool, Error> {
+    Ok(session_state(tx, session_id)? == SessionState::Open)
+}
+
+/// Returns the number of estimations the user had in the session before the
+/// vote, `None` when the session is not open and the vote was refused.
+pub fn insert_update_estimation(
+    conn: &mut Connection,
+    user_id: usize,
+    session_id: usize,
+    value: String,
+) -> Result<Option<usize>, Error> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    if !is_session_open(&tx, session_id)? {
+        return Ok(None);
+    }
+
+    let select_query = "
+        SELECT COUNT(*)
+        FROM estimations
+        WHERE session_id = ?1 AND user_id = ?2;
+    ";
+    let count = tx.query_row(
+        select_query,
+        [session_id.to_string(), user_id.to_string()],
+        |row| row.get(0),
+    )?;
+
+    let query = "
+        INSERT INTO estimations (value, session_id, user_id)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT(user_id, session_id)
+        DO UPDATE SET value = ?1
+        WHERE session_id = ?2 AND user_id = ?3;
+    ";
+
+    tx.execute(query, [value, session_id.to_string(), user_id.to_string()])?;
+    tx.commit()?;
+
+    Ok(Some(count))
+}
+
+/// Records a card press in range mode: the first press sets the lower bound,
+/// the second one the upper bound, a third press starts a new range. Returns
+/// the number of estimations the user had before the press, like
+/// `insert_update_estimation`.
+pub fn insert_update_range_estimation(
+    conn: &mut Connection,
+    user_id: usize,
+    session_id: usize,
+    value: usize,
+) -> Result<Option<usize>, Error> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    if !is_session_open(&tx, session_id)? {
+        return Ok(None);
+    }
+
+    let existing: Option<(usize, Option<usize>)> = tx
+        .query_row(
+            "SELECT value, value_max FROM estimations WHERE session_id = ?1 AND user_id = ?2;",
+            [session_id, user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    let (value, value_max) = match existing {
+        Some((low, None)) => (low.min(value), Some(low.max(value))),
+        _ => (value, None),
+    };
+
+    tx.execute(
+        "
+        INSERT INTO estimations (value, value_max, session_id, user_id)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(user_id, session_id)
+        DO UPDATE SET value = ?1, value_max = ?2
+        WHERE session_id = ?3 AND user_id = ?4;
+    ",
+        params![value, value_max, session_id, user_id],
+    )?;
+    tx.commit()?;
+
+    Ok(Some(existing.map_or(0, |_| 1)))
+}
+
+/// Attaches a confidence tag to the user's estimation. Returns 0 when the user
+/// has not voted in the session yet, `None` when the session is not open.
+pub fn save_confidence(
+    conn: &mut Connection,
+    user_id: usize,
+    session_id: usize,
+    confidence: Confidence,
+) -> Result<Option<usize>, Error> {
+    let query = "
+        UPDATE estimations
+        SET confidence = ?1
+        WHERE session_id = ?2 AND user_id = ?3;
+    ";
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    if !is_session_open(&tx, session_id)? {
+        return Ok(None);
+    }
+
+    let updated = tx.execute(query, params![confidence.as_str(), session_id, user_id])?;
+    tx.commit()?;
+
+    Ok(Some(updated))
+}
+
+/// Saves the explanation of a voter's estimate. Returns 0 when the user has not
+/// voted in the session.
+pub fn save_comment(
+    conn: &mut Connection,
+    user_id: usize,
+    session_id: usize,
+    comment: String,
+) -> Result<usize, Error> {
+    let query = "
+        UPDATE estimations
+        SET comment = ?1
+        WHERE session_id = ?2 AND user_id = ?3;
+    ";
+
+    conn.execute(query, params![comment, session_id, user_id])
+}
+
+pub fn find_estimations(
+    conn: &mut Connection,
+    session_id: usize,
+) -> Result<Vec<EstimationWithUser>, Error> {
+    let query = "
+        SELECT estimations.id, value, users.first_name, users.last_name, users.username, value_max, confidence, comment FROM estimations
         JOIN users ON estimations.user_id = users.id
         WHERE estimations.session_id = ?1
     ";
@@ -206,6 +1118,11 @@ pub fn find_estimations(
             first_name: row.get(2)?,
             last_name: row.get(3)?,
             username: row.get(4)?,
+            value_max: row.get(5)?,
+            confidence: row
+                .get::<usize, Option<String>>(6)?
+                .and_then(|raw| raw.parse().ok()),
+            comment: row.get(7)?,
         })
     })?;
 
@@ -217,29 +1134,1073 @@ pub fn find_estimations(
     Ok(result)
 }
 
-pub fn restart_session(conn: &mut Connection, session_id: usize) -> Result<usize, Error> {
+/// Starts a state transition of the session. The transaction takes the write
+/// lock right away, so of two concurrent transitions the second one sees the
+/// state left by the first. `None` when the session may not move to `state`.
+fn begin_transition(
+    conn: &mut Connection,
+    session_id: usize,
+    state: SessionState,
+) -> Result<Option<Transaction<'_>>, Error> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let current = session_state(&tx, session_id)?;
+
+    Ok(current.can_become(state).then_some(tx))
+}
+
+/// Starts a new round: the votes of a revealed one are moved to
+/// `past_estimations` so exports keep the whole history of the session. An
+/// open session starts its round over instead, dropping the votes nobody has
+/// seen. Returns `false` when the session is neither open nor revealed.
+pub fn restart_session(conn: &mut Connection, session_id: usize) -> Result<bool, Error> {
+    let archive_query = "
+        INSERT INTO past_estimations (round, value, value_max, confidence, comment, session_id, user_id)
+        SELECT sessions.round, value, value_max, confidence, comment, session_id, user_id
+        FROM estimations
+        JOIN sessions ON estimations.session_id = sessions.id
+        WHERE session_id = ?1;
+    ";
+
     let estimations_query = "
         DELETE FROM estimations
         WHERE session_id = ?1;
     ";
 
-    let finish_session_query = "
+    let restart_session_query = "
         UPDATE sessions
-        SET finished = 0
+        SET state = 'open', finished_at = NULL, round = round + ?2, deadline = unixepoch() + timer_minutes * 60
         WHERE id = ?1;
     ";
 
-    conn.execute(estimations_query, [session_id.to_string()])?;
+    let Some(tx) = begin_transition(conn, session_id, SessionState::Open)? else {
+        return Ok(false);
+    };
+    let revealed = session_state(&tx, session_id)? == SessionState::Revealed;
+
+    if revealed {
+        tx.execute(archive_query, [session_id])?;
+    }
 
-    conn.execute(finish_session_query, [session_id.to_string()])
+    tx.execute(estimations_query, [session_id])?;
+    tx.execute(
+        restart_session_query,
+        params![session_id, revealed as usize],
+    )?;
+
+    tx.commit()?;
+
+    Ok(true)
 }
 
-pub fn finish_session(conn: &mut Connection, session_id: usize) -> Result<usize, Error> {
+/// Reveals the votes. Returns `false` when the session is not open.
+pub fn finish_session(conn: &mut Connection, session_id: usize) -> Result<bool, Error> {
     let query = "
         UPDATE sessions
-        SET finished = 1
+        SET state = 'revealed', finished_at = unixepoch()
         WHERE id = ?1;
     ";
 
-    conn.execute(query, [session_id.to_string()])
+    let Some(tx) = begin_transition(conn, session_id, SessionState::Revealed)? else {
+        return Ok(false);
+    };
+
+    tx.execute(query, [session_id.to_string()])?;
+    tx.commit()?;
+
+    Ok(true)
+}
+
+/// Settles the estimate of a revealed session. Returns `false` when the
+/// session is not revealed.
+pub fn finalize_session(conn: &mut Connection, session_id: usize) -> Result<bool, Error> {
+    let Some(tx) = begin_transition(conn, session_id, SessionState::Finalized)? else {
+        return Ok(false);
+    };
+
+    tx.execute(
+        "UPDATE sessions SET state = 'finalized' WHERE id = ?1;",
+        [session_id],
+    )?;
+    tx.commit()?;
+
+    Ok(true)
+}
+
+/// Remembers the /poker message that started the session, so edits of the
+/// command can update the task.
+pub fn bind_command_message(
+    conn: &mut Connection,
+    session_id: usize,
+    tg_command_message_id: MessageId,
+) -> Result<usize, Error> {
+    conn.execute(
+        "UPDATE sessions SET tg_command_message_id = ?2 WHERE id = ?1;",
+        params![session_id, tg_command_message_id.0],
+    )
+}
+
+/// `None` when the command message did not start a session.
+pub fn find_session_by_command(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    tg_command_message_id: MessageId,
+) -> Result<Option<SessionWithInitiator>, Error> {
+    let query = format!(
+        "{} WHERE sessions.tg_chat_id = ?1 AND sessions.tg_command_message_id = ?2;",
+        SESSION_WITH_INITIATOR_QUERY
+    );
+
+    conn.query_row(
+        &query,
+        params![tg_chat_id.0, tg_command_message_id.0],
+        session_with_initiator_from_row,
+    )
+    .optional()
+}
+
+/// Replaces the task of the session, the votes stay.
+pub fn update_session_task(
+    conn: &mut Connection,
+    session_id: usize,
+    title: String,
+    description: String,
+    description_markdown: Option<String>,
+) -> Result<usize, Error> {
+    conn.execute(
+        "UPDATE sessions SET title = ?2, description = ?3, description_markdown = ?4 WHERE id = ?1;",
+        params![session_id, title, description, description_markdown],
+    )
+}
+
+/// Stores the dialogue, replacing an earlier one of the user in the chat.
+pub fn save_dialogue(conn: &mut Connection, dialogue: Dialogue) -> Result<usize, Error> {
+    conn.execute(
+        "
+        INSERT OR REPLACE INTO dialogues (tg_chat_id, tg_user_id, step, tg_prompt_message_id, tg_thread_id, title, description, deck, language)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);
+    ",
+        params![
+            dialogue.chat_id.0,
+            dialogue.user_id.0,
+            dialogue.step.as_str(),
+            dialogue.prompt_message_id.0,
+            dialogue.thread_id,
+            dialogue.title,
+            dialogue.description,
+            dialogue.deck.map(|deck| deck.as_str()),
+            dialogue.language.as_str(),
+        ],
+    )
+}
+
+/// `None` when the message is not the prompt of a dialogue.
+pub fn find_dialogue(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    tg_prompt_message_id: MessageId,
+) -> Result<Option<Dialogue>, Error> {
+    let query = "
+        SELECT tg_user_id, step, tg_thread_id, title, description, deck, language
+        FROM dialogues
+        WHERE tg_chat_id = ?1 AND tg_prompt_message_id = ?2;
+    ";
+
+    conn.query_row(
+        query,
+        params![tg_chat_id.0, tg_prompt_message_id.0],
+        |row| {
+            Ok(Dialogue {
+                chat_id: tg_chat_id,
+                user_id: UserId(row.get(0)?),
+                step: enum_from_row(row, 1)?,
+                prompt_message_id: tg_prompt_message_id,
+                thread_id: row.get(2)?,
+                title: row.get(3)?,
+                description: row.get(4)?,
+                deck: row
+                    .get::<usize, Option<String>>(5)?
+                    .and_then(|raw| raw.parse().ok()),
+                language: enum_from_row(row, 6)?,
+            })
+        },
+    )
+    .optional()
+}
+
+pub fn delete_dialogue(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    tg_user_id: UserId,
+) -> Result<usize, Error> {
+    conn.execute(
+        "DELETE FROM dialogues WHERE tg_chat_id = ?1 AND tg_user_id = ?2;",
+        params![tg_chat_id.0, tg_user_id.0],
+    )
+}
+
+/// Binds the session to a new message of its chat.
+pub fn move_session(
+    conn: &mut Connection,
+    session_id: usize,
+    tg_message_id: MessageId,
+) -> Result<usize, Error> {
+    conn.execute(
+        "UPDATE sessions SET tg_message_id = ?2 WHERE id = ?1;",
+        params![session_id, tg_message_id.0],
+    )
+}
+
+/// Returns `false` when the session is already archived.
+pub fn archive_session(conn: &mut Connection, session_id: usize) -> Result<bool, Error> {
+    let Some(tx) = begin_transition(conn, session_id, SessionState::Archived)? else {
+        return Ok(false);
+    };
+
+    tx.execute(
+        "UPDATE sessions SET state = 'archived' WHERE id = ?1;",
+        [session_id],
+    )?;
+    tx.commit()?;
+
+    Ok(true)
+}
+
+/// Returns `false` when the session is already finalized or cancelled.
+pub fn cancel_session(conn: &mut Connection, session_id: usize) -> Result<bool, Error> {
+    let query = "
+        UPDATE sessions
+        SET state = 'cancelled', finished_at = unixepoch()
+        WHERE id = ?1;
+    ";
+
+    let Some(tx) = begin_transition(conn, session_id, SessionState::Cancelled)? else {
+        return Ok(false);
+    };
+
+    tx.execute(query, [session_id])?;
+    tx.commit()?;
+
+    Ok(true)
+}
+
+/// One page of the chat's sessions, newest first, and the total number of
+/// sessions in the chat. With a topic given, only the sessions of that topic.
+pub fn find_session_history(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    tg_thread_id: Option<i32>,
+    page: usize,
+    page_size: usize,
+) -> Result<(Vec<SessionSummary>, usize), Error> {
+    let total = conn.query_row(
+        "SELECT COUNT(*) FROM sessions WHERE tg_chat_id = ?1 AND (?2 IS NULL OR tg_thread_id = ?2) AND state IN ('open', 'revealed', 'finalized');",
+        params![tg_chat_id.to_string(), tg_thread_id],
+        |row| row.get(0),
+    )?;
+
+    let query = "
+        SELECT id, tg_message_id, title, strftime('%d.%m.%Y', created_at, 'unixepoch'), state
+        FROM sessions
+        WHERE tg_chat_id = ?1 AND (?2 IS NULL OR tg_thread_id = ?2) AND state IN ('open', 'revealed', 'finalized')
+        ORDER BY id DESC
+        LIMIT ?3 OFFSET ?4;
+    ";
+
+    let mut stmt = conn.prepare(query)?;
+    let rows = stmt.query_map(
+        params![
+            tg_chat_id.to_string(),
+            tg_thread_id,
+            page_size,
+            page * page_size
+        ],
+        |row| {
+            Ok(SessionSummary {
+                session_id: row.get(0)?,
+                tg_message_id: row.get(1)?,
+                title: row.get(2)?,
+                created: row.get(3)?,
+                state: enum_from_row(row, 4)?,
+                votes: Vec::new(),
+            })
+        },
+    )?;
+
+    let mut sessions = Vec::new();
+    for row in rows {
+        sessions.push(row?)
+    }
+
+    let mut votes_stmt = conn.prepare(
+        "SELECT (value + COALESCE(value_max, value)) / 2.0 FROM estimations WHERE session_id = ?1;",
+    )?;
+
+    for session in sessions.iter_mut() {
+        let rows = votes_stmt.query_map([session.session_id], |row| row.get(0))?;
+
+        for row in rows {
+            session.votes.push(row?)
+        }
+    }
+
+    Ok((sessions, total))
+}
+
+/// Number of finished sessions each participant of the chat voted in, most
+/// active first. Anonymous sessions are left out.
+pub fn find_voter_activity(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+) -> Result<Vec<VoterActivity>, Error> {
+    let query = "
+        SELECT users.id, users.first_name, users.last_name, users.username, COUNT(DISTINCT estimations.session_id) AS sessions_voted
+        FROM estimations
+        JOIN sessions ON estimations.session_id = sessions.id
+        JOIN users ON estimations.user_id = users.id
+        WHERE sessions.tg_chat_id = ?1 AND sessions.state IN ('revealed', 'finalized') AND sessions.anonymous = 0
+        GROUP BY users.id
+        ORDER BY sessions_voted DESC;
+    ";
+
+    let mut stmt = conn.prepare(query)?;
+    let rows = stmt.query_map([tg_chat_id.to_string()], |row| {
+        Ok(VoterActivity {
+            user_db_id: row.get(0)?,
+            first_name: row.get(1)?,
+            last_name: row.get(2)?,
+            username: row.get(3)?,
+            sessions_voted: row.get(4)?,
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?)
+    }
+
+    Ok(result)
+}
+
+/// Every vote of the chat's finished sessions, except the anonymous ones.
+pub fn find_session_votes(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+) -> Result<Vec<SessionVote>, Error> {
+    let query = "
+        SELECT estimations.session_id, estimations.user_id, value, value_max
+        FROM estimations
+        JOIN sessions ON estimations.session_id = sessions.id
+        WHERE sessions.tg_chat_id = ?1 AND sessions.state IN ('revealed', 'finalized') AND sessions.anonymous = 0
+        ORDER BY estimations.session_id;
+    ";
+
+    let mut stmt = conn.prepare(query)?;
+    let rows = stmt.query_map([tg_chat_id.to_string()], |row| {
+        Ok(SessionVote {
+            session_id: row.get(0)?,
+            user_db_id: row.get(1)?,
+            value: row.get(2)?,
+            value_max: row.get(3)?,
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?)
+    }
+
+    Ok(result)
+}
+
+/// Sessions of the chat with the votes of every round, for exports. Dates are
+/// inclusive `YYYY-MM-DD` bounds on the creation date.
+pub fn find_export_sessions(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<ExportSession>, Error> {
+    let sessions_query = "
+        SELECT sessions.id, title, description, state, vote_mode, round, users.tg_id, users.first_name, users.last_name, users.username,
+            strftime('%Y-%m-%dT%H:%M:%SZ', created_at, 'unixepoch'),
+            strftime('%Y-%m-%dT%H:%M:%SZ', finished_at, 'unixepoch'),
+            anonymous
+        FROM sessions
+        JOIN users ON sessions.initiator_id = users.id
+        WHERE tg_chat_id = ?1 AND state IN ('open', 'revealed', 'finalized')
+            AND (?2 IS NULL OR date(created_at, 'unixepoch') >= ?2)
+            AND (?3 IS NULL OR date(created_at, 'unixepoch') <= ?3)
+        ORDER BY sessions.id;
+    ";
+
+    // The votes of the round still running are secret until it is revealed.
+    let votes_query = "
+        SELECT round, users.tg_id, users.first_name, users.last_name, users.username, value, value_max, confidence, comment
+        FROM (
+            SELECT sessions.round AS round, value, value_max, confidence, comment, user_id, estimations.id AS vote_id
+            FROM estimations
+            JOIN sessions ON estimations.session_id = sessions.id
+            WHERE session_id = ?1 AND sessions.state IN ('revealed', 'finalized')
+            UNION ALL
+            SELECT round, value, value_max, confidence, comment, user_id, id
+            FROM past_estimations
+            WHERE session_id = ?1
+        ) AS votes
+        JOIN users ON votes.user_id = users.id
+        ORDER BY round, vote_id;
+    ";
+
+    let mut stmt = conn.prepare(sessions_query)?;
+    let rows = stmt.query_map(params![tg_chat_id.to_string(), from, to], |row| {
+        Ok(ExportSession {
+            session_id: row.get(0)?,
+            title: row.get(1)?,
+            description: row.get(2)?,
+            state: enum_from_row(row, 3)?,
+            vote_mode: enum_from_row(row, 4)?,
+            round: row.get(5)?,
+            initiator_tg_id: row.get(6)?,
+            initiator_first_name: row.get(7)?,
+            initiator_last_name: row.get(8)?,
+            initiator_username: row.get(9)?,
+            created_at: row.get(10)?,
+            finished_at: row.get(11)?,
+            anonymous: row.get(12)?,
+            votes: Vec::new(),
+        })
+    })?;
+
+    let mut sessions = Vec::new();
+    for row in rows {
+        sessions.push(row?)
+    }
+
+    let mut votes_stmt = conn.prepare(votes_query)?;
+
+    for session in sessions.iter_mut() {
+        let rows = votes_stmt.query_map([session.session_id], |row| {
+            Ok(ExportVote {
+                round: row.get(0)?,
+                voter_tg_id: row.get(1)?,
+                first_name: row.get(2)?,
+                last_name: row.get(3)?,
+                username: row.get(4)?,
+                value: row.get(5)?,
+                value_max: row.get(6)?,
+                confidence: row.get(7)?,
+                comment: row.get(8)?,
+            })
+        })?;
+
+        for row in rows {
+            session.votes.push(row?)
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Current time as an ISO 8601 timestamp in UTC, matching the exported dates.
+pub fn current_timestamp(conn: &mut Connection) -> Result<String, Error> {
+    conn.query_row("SELECT strftime('%Y-%m-%dT%H:%M:%SZ', 'now');", [], |row| {
+        row.get(0)
+    })
+}
+
+/// Configuration the session runs under. Inline sessions belong to no chat
+/// known to the bot and get the defaults.
+pub fn find_session_config(
+    conn: &mut Connection,
+    tg_chat_id: Option<ChatId>,
+) -> Result<ChatConfig, Error> {
+    match tg_chat_id {
+        Some(tg_chat_id) => find_chat_config(conn, tg_chat_id),
+        None => Ok(ChatConfig::default()),
+    }
+}
+
+pub fn find_chat_config(conn: &mut Connection, tg_chat_id: ChatId) -> Result<ChatConfig, Error> {
+    let query = "
+        SELECT vote_mode, max_spread, max_estimate, min_voters, outlier_comments, deck, language, reveal_mode, auto_reveal, permissions, timer_minutes, anonymous
+        FROM chat_configs
+        WHERE tg_chat_id = ?1;
+    ";
+
+    let config = conn
+        .query_row(query, [tg_chat_id.to_string()], |row| {
+            Ok(ChatConfig {
+                vote_mode: enum_from_row(row, 0)?,
+                max_spread: row.get(1)?,
+                max_estimate: row.get(2)?,
+                min_voters: row.get(3)?,
+                outlier_comments: row.get(4)?,
+                deck: row
+                    .get::<usize, Option<String>>(5)?
+                    .and_then(|raw| raw.parse().ok())
+                    .unwrap_or_default(),
+                language: row
+                    .get::<usize, Option<String>>(6)?
+                    .and_then(|raw| raw.parse().ok()),
+                reveal_mode: enum_from_row(row, 7)?,
+                auto_reveal: row.get(8)?,
+                permissions: enum_from_row(row, 9)?,
+                timer_minutes: row.get(10)?,
+                anonymous: row.get(11)?,
+            })
+        })
+        .optional()?;
+
+    Ok(config.unwrap_or_default())
+}
+
+pub fn save_chat_config(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    config: ChatConfig,
+) -> Result<usize, Error> {
+    let query = "
+        INSERT INTO chat_configs (tg_chat_id, chat_id, vote_mode, max_spread, max_estimate, min_voters, outlier_comments, deck, language, reveal_mode, auto_reveal, permissions, timer_minutes, anonymous)
+        VALUES (?1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+        ON CONFLICT(tg_chat_id) DO UPDATE SET
+            vote_mode = ?2,
+            max_spread = ?3,
+            max_estimate = ?4,
+            min_voters = ?5,
+            outlier_comments = ?6,
+            deck = ?7,
+            language = ?8,
+            reveal_mode = ?9,
+            auto_reveal = ?10,
+            permissions = ?11,
+            timer_minutes = ?12,
+            anonymous = ?13;
+    ";
+
+    conn.execute(
+        query,
+        params![
+            tg_chat_id.to_string(),
+            config.vote_mode.as_str(),
+            config.max_spread,
+            config.max_estimate,
+            config.min_voters,
+            config.outlier_comments,
+            config.deck.as_str(),
+            config.language.map(|language| language.as_str()),
+            config.reveal_mode.as_str(),
+            config.auto_reveal,
+            config.permissions.as_str(),
+            config.timer_minutes,
+            config.anonymous,
+        ],
+    )
+}
+
+pub fn save_comparison(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    tg_message_id: MessageId,
+    initiator_id: usize,
+    language: Language,
+    items: Vec<String>,
+) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT INTO comparisons (tg_chat_id, tg_message_id, initiator_id, language) VALUES (?1, ?2, ?3, ?4);",
+        params![
+            tg_chat_id.to_string(),
+            tg_message_id.to_string(),
+            initiator_id,
+            language.as_str()
+        ],
+    )?;
+    let comparison_id = tx.last_insert_rowid() as usize;
+
+    for (position, title) in items.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO comparison_items (comparison_id, position, title) VALUES (?1, ?2, ?3);",
+            params![comparison_id, position, title],
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(comparison_id)
+}
+
+pub fn find_comparison(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    tg_message_id: MessageId,
+) -> Result<Option<Comparison>, Error> {
+    let query = "
+        SELECT id, current_pair, finished, initiator_id, language
+        FROM comparisons
+        WHERE tg_chat_id = ?1 AND tg_message_id = ?2;
+    ";
+
+    let comparison = conn
+        .query_row(
+            query,
+            [tg_chat_id.to_string(), tg_message_id.to_string()],
+            |row| {
+                Ok(Comparison {
+                    id: row.get(0)?,
+                    current_pair: row.get(1)?,
+                    finished: row.get(2)?,
+                    initiator_db_id: row.get(3)?,
+                    language: enum_from_row(row, 4)?,
+                    items: Vec::new(),
+                })
+            },
+        )
+        .optional()?;
+
+    let Some(mut comparison) = comparison else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT title FROM comparison_items WHERE comparison_id = ?1 ORDER BY position;",
+    )?;
+    let rows = stmt.query_map([comparison.id], |row| row.get(0))?;
+
+    for row in rows {
+        comparison.items.push(row?)
+    }
+
+    Ok(Some(comparison))
+}
+
+pub fn save_comparison_answer(
+    conn: &mut Connection,
+    comparison_id: usize,
+    pair: usize,
+    user_id: usize,
+    outcome: String,
+) -> Result<usize, Error> {
+    let query = "
+        INSERT INTO comparison_answers (comparison_id, pair, user_id, outcome)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(comparison_id, pair, user_id)
+        DO UPDATE SET outcome = ?4;
+    ";
+
+    conn.execute(query, params![comparison_id, pair, user_id, outcome])
+}
+
+pub fn count_comparison_answers(
+    conn: &mut Connection,
+    comparison_id: usize,
+    pair: usize,
+) -> Result<usize, Error> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM comparison_answers WHERE comparison_id = ?1 AND pair = ?2;",
+        [comparison_id, pair],
+        |row| row.get(0),
+    )
+}
+
+pub fn find_comparison_answers(
+    conn: &mut Connection,
+    comparison_id: usize,
+) -> Result<Vec<ComparisonAnswer>, Error> {
+    let query = "
+        SELECT pair, outcome
+        FROM comparison_answers
+        WHERE comparison_id = ?1
+        ORDER BY pair, id;
+    ";
+
+    let mut stmt = conn.prepare(query)?;
+    let rows = stmt.query_map([comparison_id], |row| {
+        Ok(ComparisonAnswer {
+            pair: row.get(0)?,
+            outcome: row.get(1)?,
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?)
+    }
+
+    Ok(result)
+}
+
+/// Moves past `current_pair`, `false` when the comparison is no longer on
+/// it, e.g. after a double click.
+pub fn advance_comparison(
+    conn: &mut Connection,
+    comparison_id: usize,
+    current_pair: usize,
+    finished: bool,
+) -> Result<bool, Error> {
+    let query = "
+        UPDATE comparisons
+        SET current_pair = current_pair + ?3, finished = ?4
+        WHERE id = ?1 AND current_pair = ?2 AND finished = 0;
+    ";
+
+    let changed = conn.execute(
+        query,
+        params![
+            comparison_id,
+            current_pair,
+            if finished { 0 } else { 1 },
+            finished as u8
+        ],
+    )?;
+
+    Ok(changed > 0)
+}
+
+pub fn save_queued_sessions(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    sessions: Vec<QueuedSession>,
+) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+
+    for session in &sessions {
+        tx.execute(
+            "INSERT INTO queued_sessions (tg_chat_id, title, description, initiator_id) VALUES (?1, ?2, ?3, ?4);",
+            params![
+                tg_chat_id.to_string(),
+                session.title,
+                session.description,
+                session.initiator_db_id
+            ],
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(sessions.len())
+}
+
+/// Removes the oldest queued task of the chat and returns it.
+pub fn pop_queued_session(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+) -> Result<Option<QueuedSession>, Error> {
+    let tx = conn.transaction()?;
+
+    let queued = tx
+        .query_row(
+            "SELECT id, title, description, initiator_id FROM queued_sessions WHERE tg_chat_id = ?1 ORDER BY id LIMIT 1;",
+            [tg_chat_id.to_string()],
+            |row| {
+                Ok((
+                    row.get::<usize, usize>(0)?,
+                    QueuedSession {
+                        title: row.get(1)?,
+                        description: row.get(2)?,
+                        initiator_db_id: row.get(3)?,
+                    },
+                ))
+            },
+        )
+        .optional()?;
+
+    if let Some((id, _)) = &queued {
+        tx.execute("DELETE FROM queued_sessions WHERE id = ?1;", [id])?;
+    }
+
+    tx.commit()?;
+
+    Ok(queued.map(|(_, session)| session))
+}
+
+/// Number of queued tasks the session can hand over to, zero unless it is the
+/// latest session of the chat.
+pub fn count_queue_after(
+    conn: &mut Connection,
+    tg_chat_id: ChatId,
+    session_id: usize,
+) -> Result<usize, Error> {
+    let query = "
+        SELECT COUNT(*)
+        FROM queued_sessions
+        WHERE tg_chat_id = ?1
+            AND NOT EXISTS (SELECT 1 FROM sessions WHERE tg_chat_id = ?1 AND id > ?2);
+    ";
+
+    conn.query_row(query, params![tg_chat_id.to_string(), session_id], |row| {
+        row.get(0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        prepare_database(&mut conn).unwrap();
+        conn
+    }
+
+    fn insert_user(conn: &mut Connection, tg_id: u64) -> usize {
+        find_or_insert_user(
+            conn,
+            UserId(tg_id),
+            format!("User {}", tg_id),
+            String::new(),
+            String::new(),
+        )
+        .unwrap()
+    }
+
+    fn insert_session(conn: &mut Connection, chat_id: ChatId, initiator_db_id: usize) -> usize {
+        save_session(
+            conn,
+            SessionWithInitiator {
+                session_id: 0,
+                title: "Task".to_string(),
+                description: String::new(),
+                description_markdown: None,
+                state: SessionState::Open,
+                vote_mode: VoteMode::Single,
+                deck: Deck::Fibonacci,
+                anonymous: false,
+                timer_minutes: None,
+                language: Language::En,
+                initiator_first_name: String::new(),
+                initiator_last_name: String::new(),
+                initiator_username: String::new(),
+                initiator_db_id,
+                message: SessionMessage::Chat(chat_id, MessageId(1)),
+                thread_id: None,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn chat_config_round_trips() {
+        let mut conn = open();
+        let chat_id = ChatId(-100123);
+
+        let config = ChatConfig {
+            vote_mode: VoteMode::Range,
+            max_spread: Some(2),
+            deck: Deck::PowersOfTwo,
+            language: Some(Language::En),
+            timer_minutes: Some(5),
+            anonymous: true,
+            ..ChatConfig::default()
+        };
+        save_chat_config(&mut conn, chat_id, config).unwrap();
+
+        // The second save updates the row instead of inserting one more.
+        let config = ChatConfig {
+            max_estimate: Some(13),
+            ..find_chat_config(&mut conn, chat_id).unwrap()
+        };
+        save_chat_config(&mut conn, chat_id, config).unwrap();
+
+        let found = find_chat_config(&mut conn, chat_id).unwrap();
+        assert_eq!(found.vote_mode, VoteMode::Range);
+        assert_eq!(found.max_spread, Some(2));
+        assert_eq!(found.max_estimate, Some(13));
+        assert_eq!(found.deck, Deck::PowersOfTwo);
+        assert_eq!(found.language, Some(Language::En));
+        assert_eq!(found.timer_minutes, Some(5));
+        assert!(found.anonymous);
+    }
+
+    #[test]
+    fn exports_only_revealed_rounds() {
+        let mut conn = open();
+        let chat_id = ChatId(-100123);
+        let user_id = insert_user(&mut conn, 1);
+        let session_id = insert_session(&mut conn, chat_id, user_id);
+
+        let export_rounds = |conn: &mut Connection| -> Vec<(usize, usize)> {
+            find_export_sessions(conn, chat_id, None, None).unwrap()[0]
+                .votes
+                .iter()
+                .map(|vote| (vote.round, vote.value))
+                .collect()
+        };
+
+        insert_update_estimation(&mut conn, user_id, session_id, "3".to_string()).unwrap();
+        assert_eq!(export_rounds(&mut conn), vec![]);
+
+        finish_session(&mut conn, session_id).unwrap();
+        assert_eq!(export_rounds(&mut conn), vec![(1, 3)]);
+
+        restart_session(&mut conn, session_id).unwrap();
+        insert_update_estimation(&mut conn, user_id, session_id, "5".to_string()).unwrap();
+        assert_eq!(export_rounds(&mut conn), vec![(1, 3)]);
+
+        finish_session(&mut conn, session_id).unwrap();
+        assert_eq!(export_rounds(&mut conn), vec![(1, 3), (2, 5)]);
+    }
+
+    fn state_and_round(conn: &mut Connection, session_id: usize) -> (SessionState, usize) {
+        conn.query_row(
+            "SELECT state, round FROM sessions WHERE id = ?1;",
+            [session_id],
+            |row| Ok((enum_from_row(row, 0)?, row.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    fn count(conn: &mut Connection, table: &str, session_id: usize) -> usize {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE session_id = ?1;", table),
+            [session_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    /// A session of a new chat moved to `state` through the regular steps.
+    fn session_in(conn: &mut Connection, state: SessionState) -> usize {
+        let user_id = insert_user(conn, 1);
+        let session_id = insert_session(conn, ChatId(-100123), user_id);
+
+        match state {
+            SessionState::Open => {}
+            SessionState::Revealed => assert!(finish_session(conn, session_id).unwrap()),
+            SessionState::Finalized => {
+                assert!(finish_session(conn, session_id).unwrap());
+                assert!(finalize_session(conn, session_id).unwrap());
+            }
+            SessionState::Cancelled => assert!(cancel_session(conn, session_id).unwrap()),
+            SessionState::Archived => assert!(archive_session(conn, session_id).unwrap()),
+        }
+
+        session_id
+    }
+
+    #[test]
+    fn state_transitions() {
+        use SessionState::*;
+
+        assert!(Open.can_become(Open));
+        assert!(Open.can_become(Revealed));
+        assert!(Revealed.can_become(Open));
+        assert!(Revealed.can_become(Finalized));
+        assert!(Revealed.can_become(Cancelled));
+        assert!(Finalized.can_become(Archived));
+
+        assert!(!Open.can_become(Finalized));
+        assert!(!Revealed.can_become(Revealed));
+        assert!(!Finalized.can_become(Open));
+        assert!(!Finalized.can_become(Cancelled));
+        assert!(!Cancelled.can_become(Open));
+        assert!(!Archived.can_become(Open));
+        assert!(!Archived.can_become(Archived));
+    }
+
+    #[test]
+    fn votes_are_refused_after_reveal() {
+        let mut conn = open();
+        let session_id = session_in(&mut conn, SessionState::Open);
+        let user_id = insert_user(&mut conn, 2);
+
+        assert_eq!(
+            insert_update_estimation(&mut conn, user_id, session_id, "3".to_string()).unwrap(),
+            Some(0)
+        );
+        assert!(finish_session(&mut conn, session_id).unwrap());
+
+        assert_eq!(
+            insert_update_estimation(&mut conn, user_id, session_id, "5".to_string()).unwrap(),
+            None
+        );
+        assert_eq!(
+            insert_update_range_estimation(&mut conn, user_id, session_id, 5).unwrap(),
+            None
+        );
+        assert_eq!(
+            save_confidence(&mut conn, user_id, session_id, Confidence::High).unwrap(),
+            None
+        );
+        assert_eq!(find_estimations(&mut conn, session_id).unwrap()[0].value, 3);
+    }
+
+    #[test]
+    fn session_finishes_once() {
+        let mut conn = open();
+        let session_id = session_in(&mut conn, SessionState::Open);
+
+        assert!(finish_session(&mut conn, session_id).unwrap());
+        assert!(!finish_session(&mut conn, session_id).unwrap());
+    }
+
+    #[test]
+    fn restart_of_a_revealed_session_keeps_its_votes() {
+        let mut conn = open();
+        let session_id = session_in(&mut conn, SessionState::Open);
+        let user_id = insert_user(&mut conn, 2);
+        insert_update_estimation(&mut conn, user_id, session_id, "3".to_string()).unwrap();
+        finish_session(&mut conn, session_id).unwrap();
+
+        assert!(restart_session(&mut conn, session_id).unwrap());
+        assert_eq!(
+            state_and_round(&mut conn, session_id),
+            (SessionState::Open, 2)
+        );
+        assert_eq!(count(&mut conn, "estimations", session_id), 0);
+        assert_eq!(count(&mut conn, "past_estimations", session_id), 1);
+    }
+
+    #[test]
+    fn restart_of_an_open_session_drops_its_votes() {
+        let mut conn = open();
+        let session_id = session_in(&mut conn, SessionState::Open);
+        let user_id = insert_user(&mut conn, 2);
+        insert_update_estimation(&mut conn, user_id, session_id, "3".to_string()).unwrap();
+
+        assert!(restart_session(&mut conn, session_id).unwrap());
+        assert_eq!(
+            state_and_round(&mut conn, session_id),
+            (SessionState::Open, 1)
+        );
+        assert_eq!(count(&mut conn, "estimations", session_id), 0);
+        assert_eq!(count(&mut conn, "past_estimations", session_id), 0);
+    }
+
+    #[test]
+    fn settled_sessions_are_not_restarted() {
+        for state in [
+            SessionState::Finalized,
+            SessionState::Cancelled,
+            SessionState::Archived,
+        ] {
+            let mut conn = open();
+            let session_id = session_in(&mut conn, state);
+
+            assert!(!restart_session(&mut conn, session_id).unwrap());
+            assert_eq!(state_and_round(&mut conn, session_id), (state, 1));
+        }
+    }
+
+    #[test]
+    fn cancel_from_each_state() {
+        for (state, cancelled) in [
+            (SessionState::Open, true),
+            (SessionState::Revealed, true),
+            (SessionState::Finalized, false),
+            (SessionState::Cancelled, false),
+            (SessionState::Archived, false),
+        ] {
+            let mut conn = open();
+            let session_id = session_in(&mut conn, state);
+
+            assert_eq!(cancel_session(&mut conn, session_id).unwrap(), cancelled);
+
+            let expected = if cancelled {
+                SessionState::Cancelled
+            } else {
+                state
+            };
+            assert_eq!(state_and_round(&mut conn, session_id).0, expected);
+        }
+    }
 }
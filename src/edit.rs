@@ -0,0 +1,160 @@
+use crate::db::{self, ChatConfig, SessionMessage, SessionState, SessionWithInitiator};
+use crate::formatting;
+use crate::helpers::{self, InTopic};
+use crate::i18n::Texts;
+use std::error::Error;
+use teloxide::prelude::*;
+use tokio_rusqlite::Connection;
+
+/// `/edit` in reply to a session message replaces the task of the session,
+/// the payload is read like the one of `/poker`.
+pub async fn edit(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    payload: String,
+    texts: &Texts,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = msg.chat.id;
+    let thread_id = helpers::topic_id(&msg);
+
+    if payload.trim().is_empty() {
+        bot.send_message(chat_id, texts.missing_task)
+            .in_topic(thread_id)
+            .await?;
+        return Ok(());
+    }
+
+    let session = match msg.reply_to_message() {
+        Some(reply) => {
+            let message = SessionMessage::Chat(chat_id, reply.id);
+
+            conn.call(move |conn| db::find_session_by_message(conn, message))
+                .await?
+        }
+        None => None,
+    };
+
+    let Some(session) = session else {
+        bot.send_message(chat_id, texts.edit_reply_required)
+            .in_topic(thread_id)
+            .await?;
+        return Ok(());
+    };
+
+    let user = msg.from().unwrap().clone();
+    let user_id = user.id;
+    let (config, user_db_id) = conn
+        .call(move |conn| {
+            Ok((
+                db::find_session_config(conn, Some(chat_id))?,
+                db::upsert_user(conn, &user)?,
+            ))
+        })
+        .await?;
+
+    if !crate::can_manage_session(&bot, &config, &session, user_id, user_db_id).await? {
+        bot.send_message(chat_id, texts.no_manage_rights)
+            .in_topic(thread_id)
+            .await?;
+        return Ok(());
+    }
+
+    update_task(
+        bot,
+        &conn,
+        session,
+        &config,
+        helpers::parse_title_and_description(&payload),
+        formatting::formatted_description(&msg),
+    )
+    .await
+}
+
+/// An edited `/poker` message updates the task of the session it started.
+/// Only the initiator can edit the message, so no rights are checked.
+pub async fn command_edited(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    payload: String,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if payload.trim().is_empty() {
+        return Ok(());
+    }
+
+    let (chat_id, message_id) = (msg.chat.id, msg.id);
+    let found = conn
+        .call(move |conn| {
+            let Some(session) = db::find_session_by_command(conn, chat_id, message_id)? else {
+                return Ok(None);
+            };
+
+            Ok(Some((
+                session,
+                db::find_session_config(conn, Some(chat_id))?,
+            )))
+        })
+        .await?;
+
+    let Some((session, config)) = found else {
+        return Ok(());
+    };
+
+    update_task(
+        bot,
+        &conn,
+        session,
+        &config,
+        helpers::parse_title_and_description(&payload),
+        formatting::formatted_description(&msg),
+    )
+    .await
+}
+
+/// Saves the new task and re-renders the session message with its votes.
+async fn update_task(
+    bot: Bot,
+    conn: &Connection,
+    session: SessionWithInitiator,
+    config: &ChatConfig,
+    (title, description): (String, String),
+    description_markdown: Option<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Telegram refuses to edit a message into the same text.
+    if session.state == SessionState::Archived
+        || (session.title == title
+            && session.description == description
+            && session.description_markdown == description_markdown)
+    {
+        return Ok(());
+    }
+
+    let session_id = session.session_id;
+    let (saved_title, saved_description, saved_markdown) = (
+        title.clone(),
+        description.clone(),
+        description_markdown.clone(),
+    );
+    let estimations = conn
+        .call(move |conn| {
+            db::update_session_task(
+                conn,
+                session_id,
+                saved_title,
+                saved_description,
+                saved_markdown,
+            )?;
+            db::find_estimations(conn, session_id)
+        })
+        .await?;
+
+    let session = SessionWithInitiator {
+        title,
+        description,
+        description_markdown,
+        ..session
+    };
+
+    crate::send_response_message(bot, conn, &session, config, Some(estimations), false).await
+}
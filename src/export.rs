@@ -0,0 +1,470 @@
+use crate::db::{self, ChatConfig, ExportSession};
+use crate::helpers::{self, InTopic};
+use crate::i18n::Texts;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use teloxide::{prelude::*, types::InputFile};
+use tokio_rusqlite::Connection;
+
+/// Version of the JSON export format, bumped on every incompatible change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Root of the JSON export. The format is described in the README.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatExport {
+    pub schema_version: u32,
+    pub chat_id: i64,
+    pub exported_at: String,
+    pub config: ConfigExport,
+    pub users: Vec<UserExport>,
+    pub sessions: Vec<SessionExport>,
+}
+
+/// Settings added after the first version of the format are optional, so
+/// older exports still read.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigExport {
+    pub vote_mode: String,
+    pub max_spread: Option<usize>,
+    pub max_estimate: Option<usize>,
+    pub min_voters: Option<usize>,
+    pub outlier_comments: bool,
+    #[serde(default)]
+    pub deck: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub reveal_mode: Option<String>,
+    #[serde(default)]
+    pub auto_reveal: Option<usize>,
+    #[serde(default)]
+    pub permissions: Option<String>,
+    #[serde(default)]
+    pub timer_minutes: Option<usize>,
+    #[serde(default)]
+    pub anonymous: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserExport {
+    pub tg_id: u64,
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionExport {
+    pub id: usize,
+    pub title: String,
+    pub description: String,
+    /// `open` or `finished`.
+    pub status: String,
+    pub vote_mode: String,
+    /// Votes of anonymous sessions carry no voter.
+    #[serde(default)]
+    pub anonymous: bool,
+    pub initiator_tg_id: u64,
+    pub created_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub final_estimate: Option<f64>,
+    pub rounds: Vec<RoundExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoundExport {
+    pub number: usize,
+    pub votes: Vec<VoteExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoteExport {
+    pub voter_tg_id: Option<u64>,
+    pub value: usize,
+    pub value_max: Option<usize>,
+    pub confidence: Option<String>,
+    pub comment: Option<String>,
+}
+
+enum Format {
+    Csv,
+    Json,
+}
+
+const CSV_HEADER: [&str; 10] = [
+    "session_id",
+    "title",
+    "description",
+    "initiator",
+    "created_at",
+    "finished_at",
+    "round",
+    "voter",
+    "value",
+    "final_estimate",
+];
+
+pub async fn export(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    payload: String,
+    texts: &Texts,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some((format, from, to)) = parse_arguments(&payload) else {
+        bot.send_message(msg.chat.id, texts.export_usage)
+            .in_topic(helpers::topic_id(&msg))
+            .await?;
+        return Ok(());
+    };
+
+    let chat_id = msg.chat.id;
+    let (sessions, config, exported_at) = conn
+        .call(move |conn| {
+            Ok((
+                db::find_export_sessions(conn, chat_id, from, to)?,
+                db::find_chat_config(conn, chat_id)?,
+                db::current_timestamp(conn)?,
+            ))
+        })
+        .await?;
+
+    if sessions.is_empty() {
+        bot.send_message(chat_id, texts.nothing_to_export)
+            .in_topic(helpers::topic_id(&msg))
+            .await?;
+        return Ok(());
+    }
+
+    let document = match format {
+        Format::Csv => InputFile::memory(to_csv(&sessions)?).file_name("poker-export.csv"),
+        Format::Json => {
+            let export = to_chat_export(chat_id, exported_at, &config, &sessions);
+
+            InputFile::memory(serde_json::to_vec_pretty(&export)?).file_name("poker-export.json")
+        }
+    };
+
+    bot.send_document(chat_id, document)
+        .in_topic(helpers::topic_id(&msg))
+        .await?;
+
+    Ok(())
+}
+
+/// Optional format followed by optional inclusive bounds given as
+/// `YYYY-MM-DD`, `None` when the arguments are malformed.
+fn parse_arguments(payload: &str) -> Option<(Format, Option<String>, Option<String>)> {
+    let date_re = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
+    let args: Vec<&str> = payload.split_whitespace().collect();
+
+    let (format, dates) = match args.split_first() {
+        Some((&"json", dates)) => (Format::Json, dates),
+        Some((&"csv", dates)) => (Format::Csv, dates),
+        _ => (Format::Csv, args.as_slice()),
+    };
+
+    if dates.len() > 2 || !dates.iter().all(|date| date_re.is_match(date)) {
+        return None;
+    }
+
+    Some((
+        format,
+        dates.first().map(|date| date.to_string()),
+        dates.get(1).map(|date| date.to_string()),
+    ))
+}
+
+fn to_chat_export(
+    chat_id: ChatId,
+    exported_at: String,
+    config: &ChatConfig,
+    sessions: &[ExportSession],
+) -> ChatExport {
+    let mut users: Vec<UserExport> = Vec::new();
+    let mut add_user = |tg_id: u64, first_name: &str, last_name: &str, username: &str| {
+        if users.iter().all(|user| user.tg_id != tg_id) {
+            users.push(UserExport {
+                tg_id,
+                first_name: first_name.to_string(),
+                last_name: Some(last_name.to_string()).filter(|name| !name.is_empty()),
+                username: Some(username.to_string()).filter(|name| !name.is_empty()),
+            });
+        }
+    };
+
+    for session in sessions {
+        add_user(
+            session.initiator_tg_id,
+            &session.initiator_first_name,
+            &session.initiator_last_name,
+            &session.initiator_username,
+        );
+
+        if session.anonymous {
+            continue;
+        }
+
+        for vote in &session.votes {
+            add_user(
+                vote.voter_tg_id,
+                &vote.first_name,
+                &vote.last_name,
+                &vote.username,
+            );
+        }
+    }
+
+    let sessions = sessions
+        .iter()
+        .map(|session| SessionExport {
+            id: session.session_id,
+            title: session.title.clone(),
+            description: session.description.clone(),
+            status: if session.state.is_revealed() {
+                "finished".to_string()
+            } else {
+                "open".to_string()
+            },
+            vote_mode: session.vote_mode.as_str().to_string(),
+            anonymous: session.anonymous,
+            initiator_tg_id: session.initiator_tg_id,
+            created_at: session.created_at.clone(),
+            finished_at: session.finished_at.clone(),
+            final_estimate: final_estimate(session),
+            rounds: (1..=revealed_rounds(session))
+                .map(|number| RoundExport {
+                    number,
+                    votes: session
+                        .votes
+                        .iter()
+                        .filter(|vote| vote.round == number)
+                        .map(|vote| VoteExport {
+                            voter_tg_id: Some(vote.voter_tg_id).filter(|_| !session.anonymous),
+                            value: vote.value,
+                            value_max: vote.value_max,
+                            confidence: vote.confidence.clone(),
+                            comment: vote.comment.clone(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    ChatExport {
+        schema_version: SCHEMA_VERSION,
+        chat_id: chat_id.0,
+        exported_at,
+        config: ConfigExport {
+            vote_mode: config.vote_mode.as_str().to_string(),
+            max_spread: config.max_spread,
+            max_estimate: config.max_estimate,
+            min_voters: config.min_voters,
+            outlier_comments: config.outlier_comments,
+            deck: Some(config.deck.as_str().to_string()),
+            language: config
+                .language
+                .map(|language| language.as_str().to_string()),
+            reveal_mode: Some(config.reveal_mode.as_str().to_string()),
+            auto_reveal: config.auto_reveal,
+            permissions: Some(config.permissions.as_str().to_string()),
+            timer_minutes: config.timer_minutes,
+            anonymous: Some(config.anonymous),
+        },
+        users,
+        sessions,
+    }
+}
+
+/// One row per vote, sessions without votes get a single row with empty
+/// voter columns. The voter column stays empty for anonymous sessions.
+fn to_csv(sessions: &[ExportSession]) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(CSV_HEADER)?;
+
+    for session in sessions {
+        let initiator = helpers::make_username_line(
+            &session.initiator_first_name,
+            &session.initiator_last_name,
+            &session.initiator_username,
+        );
+        let final_estimate = final_estimate(session)
+            .map(|estimate| estimate.to_string())
+            .unwrap_or_default();
+
+        let session_columns = [
+            session.session_id.to_string(),
+            session.title.clone(),
+            session.description.clone(),
+            initiator,
+            session.created_at.clone().unwrap_or_default(),
+            session.finished_at.clone().unwrap_or_default(),
+        ];
+
+        if session.votes.is_empty() {
+            writer.write_record(
+                session_columns
+                    .iter()
+                    .cloned()
+                    .chain([session.round.to_string(), "".into(), "".into()])
+                    .chain([final_estimate.clone()]),
+            )?;
+        }
+
+        for vote in &session.votes {
+            writer.write_record(
+                session_columns
+                    .iter()
+                    .cloned()
+                    .chain([
+                        vote.round.to_string(),
+                        if session.anonymous {
+                            "".to_string()
+                        } else {
+                            helpers::make_username_line(
+                                &vote.first_name,
+                                &vote.last_name,
+                                &vote.username,
+                            )
+                        },
+                        helpers::format_range(vote.value, vote.value_max.unwrap_or(vote.value)),
+                    ])
+                    .chain([final_estimate.clone()]),
+            )?;
+        }
+    }
+
+    writer
+        .into_inner()
+        .map_err(|error| csv::Error::from(error.into_error()))
+}
+
+/// Number of rounds whose votes can be shown: the round an open session is
+/// still running stays secret.
+fn revealed_rounds(session: &ExportSession) -> usize {
+    if session.state.is_revealed() {
+        session.round
+    } else {
+        session.round - 1
+    }
+}
+
+/// Median of the last round of a finished session.
+pub fn final_estimate(session: &ExportSession) -> Option<f64> {
+    if !session.state.is_revealed() {
+        return None;
+    }
+
+    let midpoints: Vec<f64> = session
+        .votes
+        .iter()
+        .filter(|vote| vote.round == session.round)
+        .map(|vote| (vote.value + vote.value_max.unwrap_or(vote.value)) as f64 / 2.0)
+        .collect();
+
+    helpers::median(&midpoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{ExportVote, SessionState, VoteMode};
+    use serde_json::json;
+
+    fn session(
+        session_id: usize,
+        state: SessionState,
+        round: usize,
+        anonymous: bool,
+        votes: Vec<ExportVote>,
+    ) -> ExportSession {
+        ExportSession {
+            session_id,
+            title: format!("Task {}", session_id),
+            description: String::new(),
+            state,
+            vote_mode: VoteMode::Single,
+            round,
+            anonymous,
+            initiator_tg_id: 1,
+            initiator_first_name: "Ann".to_string(),
+            initiator_last_name: String::new(),
+            initiator_username: "ann".to_string(),
+            created_at: Some("2026-10-18T12:00:00Z".to_string()),
+            finished_at: None,
+            votes,
+        }
+    }
+
+    fn vote(round: usize, voter_tg_id: u64, value: usize) -> ExportVote {
+        ExportVote {
+            round,
+            voter_tg_id,
+            first_name: format!("User {}", voter_tg_id),
+            last_name: String::new(),
+            username: String::new(),
+            value,
+            value_max: None,
+            confidence: None,
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn json_shape() {
+        let sessions = [
+            session(1, SessionState::Revealed, 1, false, vec![vote(1, 2, 3)]),
+            session(2, SessionState::Open, 2, false, vec![vote(1, 2, 5)]),
+            session(3, SessionState::Finalized, 1, true, vec![vote(1, 3, 8)]),
+        ];
+        let export = to_chat_export(
+            ChatId(-100123),
+            "2026-10-18T13:00:00Z".to_string(),
+            &ChatConfig::default(),
+            &sessions,
+        );
+        let value = serde_json::to_value(&export).unwrap();
+
+        assert_eq!(value["schema_version"], json!(SCHEMA_VERSION));
+        assert_eq!(value["chat_id"], json!(-100123));
+        assert_eq!(value["config"]["deck"], json!("fibonacci"));
+        assert_eq!(
+            value["users"],
+            json!([
+                {"tg_id": 1, "first_name": "Ann", "last_name": null, "username": "ann"},
+                {"tg_id": 2, "first_name": "User 2", "last_name": null, "username": null},
+            ])
+        );
+
+        assert_eq!(value["sessions"][0]["status"], json!("finished"));
+        assert_eq!(value["sessions"][0]["final_estimate"], json!(3.0));
+        assert_eq!(
+            value["sessions"][0]["rounds"],
+            json!([{"number": 1, "votes": [
+                {"voter_tg_id": 2, "value": 3, "value_max": null, "confidence": null, "comment": null},
+            ]}])
+        );
+
+        // The second round is still running.
+        assert_eq!(value["sessions"][1]["status"], json!("open"));
+        assert_eq!(value["sessions"][1]["final_estimate"], json!(null));
+        assert_eq!(value["sessions"][1]["rounds"].as_array().unwrap().len(), 1);
+        assert_eq!(value["sessions"][1]["rounds"][0]["number"], json!(1));
+
+        assert_eq!(value["sessions"][2]["anonymous"], json!(true));
+        assert_eq!(
+            value["sessions"][2]["rounds"][0]["votes"][0]["voter_tg_id"],
+            json!(null)
+        );
+    }
+
+    #[test]
+    fn open_session_in_its_first_round_has_no_rounds() {
+        let sessions = [session(1, SessionState::Open, 1, false, Vec::new())];
+        let export = to_chat_export(ChatId(1), String::new(), &ChatConfig::default(), &sessions);
+
+        assert!(export.sessions[0].rounds.is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use std::ops::Range;
+use teloxide::{
+    types::{Message, MessageEntityKind, MessageEntityRef},
+    utils::markdown,
+};
+
+/// Markup around a formatted part of the text.
+struct Span {
+    range: Range<usize>,
+    open: String,
+    close: String,
+    /// Code is escaped differently and cannot hold other formatting.
+    is_code: bool,
+}
+
+/// The description of a `/poker` or `/edit` message, the text after its
+/// first line, as MarkdownV2 with the formatting the user applied. `None`
+/// when the description has no formatting, the plain one is shown then.
+pub fn formatted_description(msg: &Message) -> Option<String> {
+    let text = msg.text()?;
+    let start = text.find('\n')? + 1;
+    let entities = msg.parse_entities()?;
+
+    to_markdown(text, &entities, start..text.len())
+}
+
+/// MarkdownV2 for a part of a message text. Mentions, links and hashtags need
+/// no markup since Telegram finds them again in the sent text. Entities that
+/// overlap without nesting cannot be expressed and are dropped.
+fn to_markdown(text: &str, entities: &[MessageEntityRef], range: Range<usize>) -> Option<String> {
+    let mut spans: Vec<Span> = entities
+        .iter()
+        .filter_map(|entity| {
+            let start = entity.start().max(range.start);
+            let end = entity.end().min(range.end);
+
+            if start >= end {
+                return None;
+            }
+
+            let (open, close, is_code) = match entity.kind() {
+                MessageEntityKind::Bold => ("*".to_string(), "*".to_string(), false),
+                MessageEntityKind::Italic => ("_".to_string(), "_".to_string(), false),
+                MessageEntityKind::Underline => ("__".to_string(), "__".to_string(), false),
+                MessageEntityKind::Strikethrough => ("~".to_string(), "~".to_string(), false),
+                MessageEntityKind::Spoiler => ("||".to_string(), "||".to_string(), false),
+                MessageEntityKind::Code => ("`".to_string(), "`".to_string(), true),
+                MessageEntityKind::Pre { language } => (
+                    format!(
+                        "```{}\n",
+                        markdown::escape(language.as_deref().unwrap_or(""))
+                    ),
+                    "\n```".to_string(),
+                    true,
+                ),
+                MessageEntityKind::TextLink { url } => (
+                    "[".to_string(),
+                    format!("]({})", markdown::escape_link_url(url.as_str())),
+                    false,
+                ),
+                MessageEntityKind::TextMention { user } => {
+                    ("[".to_string(), format!("]({})", user.url()), false)
+                }
+                _ => return None,
+            };
+
+            Some(Span {
+                range: start..end,
+                open,
+                close,
+                is_code,
+            })
+        })
+        .collect();
+
+    // Outer spans first, so every span can be checked against the innermost
+    // one still open around it.
+    spans.sort_by_key(|span| (span.range.start, std::cmp::Reverse(span.range.end)));
+
+    let mut kept: Vec<Span> = Vec::new();
+    let mut open: Vec<Range<usize>> = Vec::new();
+    let mut open_is_code: Vec<bool> = Vec::new();
+
+    for span in spans {
+        while open
+            .last()
+            .is_some_and(|outer| outer.end <= span.range.start)
+        {
+            open.pop();
+            open_is_code.pop();
+        }
+
+        let crosses = open.last().is_some_and(|outer| outer.end < span.range.end);
+        if crosses || open_is_code.last() == Some(&true) {
+            continue;
+        }
+
+        open.push(span.range.clone());
+        open_is_code.push(span.is_code);
+        kept.push(span);
+    }
+
+    if kept.is_empty() {
+        return None;
+    }
+
+    let mut bounds: Vec<usize> = kept
+        .iter()
+        .flat_map(|span| [span.range.start, span.range.end])
+        .chain([range.start, range.end])
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut result = String::new();
+
+    for (idx, &position) in bounds.iter().enumerate() {
+        // Innermost spans close first.
+        for span in kept.iter().rev().filter(|span| span.range.end == position) {
+            push_markup(&mut result, &span.close);
+        }
+
+        for span in kept.iter().filter(|span| span.range.start == position) {
+            push_markup(&mut result, &span.open);
+        }
+
+        let Some(&next) = bounds.get(idx + 1) else {
+            break;
+        };
+
+        let part = &text[position..next];
+        let in_code = kept
+            .iter()
+            .any(|span| span.is_code && span.range.start <= position && next <= span.range.end);
+
+        if in_code {
+            result.push_str(&markdown::escape_code(part));
+        } else {
+            result.push_str(&markdown::escape(part));
+        }
+    }
+
+    Some(result)
+}
+
+/// Separates adjacent italic and underline markers with a character Telegram
+/// ignores, `___` would be read as underline followed by italic.
+fn push_markup(result: &mut String, markup: &str) {
+    if markup.starts_with('_') && result.ends_with('_') {
+        result.push('\r');
+    }
+
+    result.push_str(markup);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::types::MessageEntity;
+
+    /// Markdown of the text after the first line, like for a `/poker` message.
+    fn description(text: &str, entities: &[MessageEntity]) -> Option<String> {
+        let entities = MessageEntityRef::parse(text, entities);
+        let start = text.find('\n').unwrap() + 1;
+
+        to_markdown(text, &entities, start..text.len())
+    }
+
+    fn entity(kind: MessageEntityKind, offset: usize, length: usize) -> MessageEntity {
+        MessageEntity::new(kind, offset, length)
+    }
+
+    #[test]
+    fn keeps_nested_entities() {
+        let text = "T\nbold italic rest";
+
+        assert_eq!(
+            description(
+                text,
+                &[
+                    entity(MessageEntityKind::Bold, 2, 11),
+                    entity(MessageEntityKind::Italic, 7, 6),
+                ],
+            ),
+            Some("*bold _italic_* rest".to_string())
+        );
+    }
+
+    #[test]
+    fn drops_crossing_entities() {
+        let text = "T\nbold italic rest";
+
+        assert_eq!(
+            description(
+                text,
+                &[
+                    entity(MessageEntityKind::Bold, 2, 6),
+                    entity(MessageEntityKind::Italic, 7, 6),
+                ],
+            ),
+            Some("*bold i*talic rest".to_string())
+        );
+    }
+
+    #[test]
+    fn drops_formatting_inside_code() {
+        let text = "T\nlet x = 1;";
+
+        assert_eq!(
+            description(
+                text,
+                &[
+                    entity(MessageEntityKind::Code, 2, 10),
+                    entity(MessageEntityKind::Bold, 6, 1),
+                ],
+            ),
+            Some("`let x = 1;`".to_string())
+        );
+    }
+
+    #[test]
+    fn separates_underline_from_italic() {
+        let text = "T\nboth";
+
+        assert_eq!(
+            description(
+                text,
+                &[
+                    entity(MessageEntityKind::Underline, 2, 4),
+                    entity(MessageEntityKind::Italic, 2, 4),
+                ],
+            ),
+            Some("__\r_both_\r__".to_string())
+        );
+    }
+
+    #[test]
+    fn escapes_text_code_and_links() {
+        let text = "T\nv1.2 (beta) a_b`c\\ docs";
+        let url = "https://example.com/a_(b)".parse().unwrap();
+
+        assert_eq!(
+            description(
+                text,
+                &[
+                    entity(MessageEntityKind::Code, 14, 6),
+                    entity(MessageEntityKind::TextLink { url }, 21, 4),
+                ],
+            ),
+            Some(r"v1\.2 \(beta\) `a_b\`c\\` [docs](https://example.com/a_(b\))".to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_the_language_of_code_blocks() {
+        let text = "T\nfn main() {}";
+        let language = Some("rust".to_string());
+
+        assert_eq!(
+            description(text, &[entity(MessageEntityKind::Pre { language }, 2, 12)]),
+            Some("```rust\nfn main() {}\n```".to_string())
+        );
+    }
+
+    #[test]
+    fn counts_offsets_in_utf16() {
+        let text = "T\n😀 жирный";
+
+        assert_eq!(
+            description(text, &[entity(MessageEntityKind::Bold, 5, 6)]),
+            Some("😀 *жирный*".to_string())
+        );
+    }
+
+    #[test]
+    fn ignores_formatting_of_the_title() {
+        let text = "Title\nplain";
+
+        assert_eq!(
+            description(text, &[entity(MessageEntityKind::Bold, 0, 5)]),
+            None
+        );
+    }
+}
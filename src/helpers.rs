@@ -1,7 +1,12 @@
 use crate::constants::{ISSUE_ID_REGEX, URL_REGEX};
 use regex::{Match, Regex};
+use teloxide::{
+    payloads::{SendDocument, SendMessage},
+    requests::{JsonRequest, MultipartRequest},
+    types::{Chat, Message, MessageId, MessageKind},
+};
 
-pub fn extract_issue_id(url: &str) -> Option<Match> {
+pub fn extract_issue_id(url: &str) -> Option<Match<'_>> {
     let re = Regex::new(ISSUE_ID_REGEX).unwrap();
     re.find(url)
 }
@@ -10,8 +15,8 @@ pub fn is_url_valid(url: &str) -> bool {
     Regex::new(URL_REGEX).unwrap().is_match(url)
 }
 
-pub fn parse_title_and_description(payload: &String) -> (String, String) {
-    let mut str_iter = payload.splitn(2, "\n");
+pub fn parse_title_and_description(payload: &str) -> (String, String) {
+    let mut str_iter = payload.splitn(2, '\n');
     let iter_count = str_iter.clone().count();
 
     let (title, description) = if iter_count == 1 {
@@ -23,7 +28,26 @@ pub fn parse_title_and_description(payload: &String) -> (String, String) {
     (title.to_string(), description.to_string())
 }
 
-pub fn make_username_line(first_name: &String, last_name: &String, username: &String) -> String {
+/// Task of a message posted by someone else: its first link becomes the
+/// title and the rest of the text the description. Without a link the text
+/// is read like a /poker payload.
+pub fn task_from_message(text: &str) -> Option<(String, String)> {
+    let text = text.trim();
+
+    if text.is_empty() {
+        return None;
+    }
+
+    let Some(url) = Regex::new(URL_REGEX).unwrap().find(text) else {
+        return Some(parse_title_and_description(text));
+    };
+
+    let description = format!("{}{}", &text[..url.start()], &text[url.end()..]);
+
+    Some((url.as_str().to_string(), description.trim().to_string()))
+}
+
+pub fn make_username_line(first_name: &str, last_name: &str, username: &str) -> String {
     let mut line = String::from(first_name);
 
     if !last_name.is_empty() {
@@ -36,3 +60,117 @@ pub fn make_username_line(first_name: &String, last_name: &String, username: &St
 
     line
 }
+
+/// Returns the interval shared by all ranges, if there is one.
+pub fn ranges_overlap(ranges: &[(usize, usize)]) -> Option<(usize, usize)> {
+    let low = ranges.iter().map(|(low, _)| *low).max()?;
+    let high = ranges.iter().map(|(_, high)| *high).min()?;
+
+    if low <= high {
+        Some((low, high))
+    } else {
+        None
+    }
+}
+
+pub fn format_range(low: usize, high: usize) -> String {
+    if low == high {
+        low.to_string()
+    } else {
+        format!("{}–{}", low, high)
+    }
+}
+
+/// Distance in cards between the lowest and the highest value, following the
+/// deck order. Values missing from the deck are ignored.
+pub fn spread_in_cards(deck: &[&str], values: &[usize]) -> Option<usize> {
+    let positions: Vec<usize> = values
+        .iter()
+        .filter_map(|value| {
+            deck.iter()
+                .position(|card| card.parse::<usize>().ok() == Some(*value))
+        })
+        .collect();
+
+    Some(positions.iter().max()? - positions.iter().min()?)
+}
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let middle = sorted.len() / 2;
+
+    match sorted.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sorted[middle - 1] + sorted[middle]) / 2.0),
+        _ => Some(sorted[middle]),
+    }
+}
+
+/// Link to a message, available for public chats and supergroups only.
+pub fn message_link(chat: &Chat, message_id: MessageId) -> Option<String> {
+    if let Some(username) = chat.username() {
+        return Some(format!("https://t.me/{}/{}", username, message_id.0));
+    }
+
+    chat.id
+        .0
+        .to_string()
+        .strip_prefix("-100")
+        .map(|internal_id| format!("https://t.me/c/{}/{}", internal_id, message_id.0))
+}
+
+/// Forum topic of the message, `None` in the general topic and in chats
+/// without topics.
+pub fn topic_id(msg: &Message) -> Option<i32> {
+    match &msg.kind {
+        MessageKind::Common(common) if common.is_topic_message => msg.thread_id,
+        _ => None,
+    }
+}
+
+/// Sends a request to the given forum topic. The generated setters only take
+/// a topic, while most chats have none.
+pub trait InTopic {
+    fn in_topic(self, thread_id: Option<i32>) -> Self;
+}
+
+impl InTopic for JsonRequest<SendMessage> {
+    fn in_topic(mut self, thread_id: Option<i32>) -> Self {
+        self.message_thread_id = thread_id;
+        self
+    }
+}
+
+impl InTopic for MultipartRequest<SendDocument> {
+    fn in_topic(mut self, thread_id: Option<i32>) -> Self {
+        self.message_thread_id = thread_id;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_interval_shared_by_all_ranges() {
+        assert_eq!(ranges_overlap(&[(1, 5), (3, 8), (2, 3)]), Some((3, 3)));
+        assert_eq!(ranges_overlap(&[(3, 5), (3, 5)]), Some((3, 5)));
+        assert_eq!(ranges_overlap(&[(5, 5)]), Some((5, 5)));
+    }
+
+    #[test]
+    fn finds_no_interval_for_disjoint_ranges() {
+        assert_eq!(ranges_overlap(&[(1, 2), (3, 5)]), None);
+        assert_eq!(ranges_overlap(&[(1, 8), (2, 3), (5, 13)]), None);
+        assert_eq!(ranges_overlap(&[]), None);
+    }
+
+    #[test]
+    fn formats_ranges_and_single_values() {
+        assert_eq!(format_range(3, 5), "3–5");
+        assert_eq!(format_range(8, 8), "8");
+    }
+}
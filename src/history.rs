@@ -0,0 +1,148 @@
+use crate::db::{self, SessionSummary};
+use crate::helpers::{self, InTopic};
+use crate::i18n::{self, Texts};
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{Chat, InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode},
+    utils::markdown,
+};
+use tokio_rusqlite::Connection;
+
+const PAGE_SIZE: usize = 5;
+
+pub fn is_history_data(data: &str) -> bool {
+    data.starts_with("hist_")
+}
+
+pub async fn show(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    texts: &Texts,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let thread_id = helpers::topic_id(&msg);
+    let (text, keyboard) = render_page(&conn, &msg.chat, thread_id, 0, texts).await?;
+
+    bot.parse_mode(ParseMode::MarkdownV2)
+        .send_message(msg.chat.id, text)
+        .reply_markup(keyboard)
+        .disable_web_page_preview(true)
+        .in_topic(helpers::topic_id(&msg))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    bot.answer_callback_query(q.id).await?;
+
+    let (Some(message), Some(page)) = (
+        q.message,
+        q.data
+            .as_deref()
+            .and_then(|data| data.strip_prefix("hist_"))
+            .and_then(|page| page.parse().ok()),
+    ) else {
+        return Ok(());
+    };
+
+    let chat_id = message.chat.id;
+    let config = conn
+        .call(move |conn| db::find_chat_config(conn, chat_id))
+        .await?;
+    let texts = i18n::texts(i18n::resolve(config.language, Some(&q.from)));
+
+    let thread_id = helpers::topic_id(&message);
+    let (text, keyboard) = render_page(&conn, &message.chat, thread_id, page, texts).await?;
+
+    bot.parse_mode(ParseMode::MarkdownV2)
+        .edit_message_text(message.chat.id, message.id, text)
+        .reply_markup(keyboard)
+        .disable_web_page_preview(true)
+        .await?;
+
+    Ok(())
+}
+
+/// Sessions of the topic only, when the history is shown in one.
+async fn render_page(
+    conn: &Connection,
+    chat: &Chat,
+    thread_id: Option<i32>,
+    page: usize,
+    texts: &Texts,
+) -> Result<(String, InlineKeyboardMarkup), tokio_rusqlite::Error> {
+    let chat_id = chat.id;
+    let (sessions, total) = conn
+        .call(move |conn| db::find_session_history(conn, chat_id, thread_id, page, PAGE_SIZE))
+        .await?;
+
+    if sessions.is_empty() {
+        return Ok((
+            markdown::escape(texts.no_history),
+            InlineKeyboardMarkup::default(),
+        ));
+    }
+
+    let pages = total.div_ceil(PAGE_SIZE);
+    let lines: Vec<String> = sessions
+        .iter()
+        .map(|session| render_session(chat, session, texts))
+        .collect();
+
+    let text = format!(
+        "{}\n\n{}",
+        markdown::bold(markdown::escape(&(texts.history_heading)(page + 1, pages)).as_str()),
+        lines.join("\n\n")
+    );
+
+    let mut row = Vec::new();
+
+    if page > 0 {
+        row.push(InlineKeyboardButton::callback(
+            texts.newer_button,
+            format!("hist_{}", page - 1),
+        ));
+    }
+
+    if page + 1 < pages {
+        row.push(InlineKeyboardButton::callback(
+            texts.older_button,
+            format!("hist_{}", page + 1),
+        ));
+    }
+
+    Ok((text, InlineKeyboardMarkup::new(vec![row])))
+}
+
+fn render_session(chat: &Chat, session: &SessionSummary, texts: &Texts) -> String {
+    let title = match helpers::message_link(chat, MessageId(session.tg_message_id)) {
+        Some(link) => markdown::link(&link, markdown::escape(&session.title).as_str()),
+        None => markdown::escape(&session.title),
+    };
+
+    let result = match (session.state.is_revealed(), helpers::median(&session.votes)) {
+        (true, Some(median)) => (texts.median)(median),
+        (true, None) => texts.without_votes.to_string(),
+        (false, _) => texts.voting_in_progress.to_string(),
+    };
+
+    format!(
+        "{}\n{}",
+        title,
+        markdown::escape(
+            format!(
+                "{} · {} · {}",
+                session.created.as_deref().unwrap_or(texts.unknown_date),
+                result,
+                (texts.votes_count)(session.votes.len())
+            )
+            .as_str()
+        )
+    )
+}
@@ -0,0 +1,636 @@
+use crate::db::Language;
+use teloxide::types::User;
+
+/// Every user-facing text of the bot in one language. Texts with values are
+/// functions, so each language puts the values where its grammar needs them.
+pub struct Texts {
+    pub help_header: &'static str,
+    /// Descriptions of the bot commands, in the order of `Command`.
+    pub commands: [(&'static str, &'static str); 13],
+    pub command_not_found: &'static str,
+    pub missing_task: &'static str,
+    pub admin_only: &'static str,
+    pub pick_estimate_first: &'static str,
+    pub confidence_saved: &'static str,
+    pub queue_empty: &'static str,
+    pub unknown_session: &'static str,
+    pub inline_feedback_required: &'static str,
+    pub unknown_button: &'static str,
+    pub outdated_buttons: &'static str,
+
+    pub session_heading: &'static str,
+    pub initiator: &'static str,
+    pub estimates: &'static str,
+    pub session_cancelled: &'static str,
+    pub spread_too_big: &'static str,
+    pub common_range: &'static str,
+    pub no_common_range: &'static str,
+    pub range_hint: &'static str,
+    pub anonymous_hint: &'static str,
+    pub timer_hint: fn(usize) -> String,
+    pub auto_reveal_hint: fn(usize) -> String,
+    pub weighted_average: &'static str,
+    pub low_confidence: fn(usize, usize) -> String,
+    /// Labels of the low, medium and high confidence buttons.
+    pub confidence_buttons: [&'static str; 3],
+    /// Labels of the restart and finish buttons.
+    pub func_buttons: [&'static str; 2],
+    pub new_round_button: &'static str,
+    pub next_session_button: &'static str,
+    pub inline_title: fn(&str) -> String,
+    pub inline_description: &'static str,
+
+    pub estimate_too_big: fn(usize) -> String,
+    pub too_few_voters: fn(usize, usize) -> String,
+    pub missing_comments: fn(&str) -> String,
+
+    pub session_not_found: &'static str,
+    pub no_manage_rights: &'static str,
+    pub session_finished: &'static str,
+    pub no_active_sessions: &'static str,
+    pub nothing_to_bump: &'static str,
+    pub edit_reply_required: &'static str,
+    pub wizard_title_prompt: &'static str,
+    pub wizard_description_prompt: &'static str,
+    pub wizard_deck_prompt: &'static str,
+    pub wizard_timer_prompt: &'static str,
+    pub wizard_expired: &'static str,
+    pub wizard_not_yours: &'static str,
+    pub active_heading: &'static str,
+    pub finish_session_button: fn(&str) -> String,
+    pub cancel_session_button: &'static str,
+    pub delete_session_button: &'static str,
+
+    pub compare_usage: fn(usize) -> String,
+    pub unknown_comparison: &'static str,
+    pub comparison_finished: &'static str,
+    pub comparison_pair_passed: &'static str,
+    pub compare_initiator_only: &'static str,
+    pub compare_pair: fn(usize, usize, &str, &str, usize) -> String,
+    pub comparison_without_answers: &'static str,
+    pub ranking_heading: &'static str,
+    pub ranking_line: fn(usize, &str, &str, f64) -> String,
+    /// Labels of the left, same and right answer buttons.
+    pub compare_answer_buttons: [&'static str; 3],
+    /// Labels of the next pair and finish buttons.
+    pub compare_func_buttons: [&'static str; 2],
+
+    pub export_usage: &'static str,
+    pub nothing_to_export: &'static str,
+
+    pub no_history: &'static str,
+    pub history_heading: fn(usize, usize) -> String,
+    pub newer_button: &'static str,
+    pub older_button: &'static str,
+    pub median: fn(f64) -> String,
+    pub without_votes: &'static str,
+    pub voting_in_progress: &'static str,
+    pub votes_count: fn(usize) -> String,
+    pub unknown_date: &'static str,
+
+    pub import_usage: &'static str,
+    pub import_not_allowed: &'static str,
+    pub next_not_allowed: &'static str,
+    pub file_too_big: &'static str,
+    pub unreadable_file: fn(&str) -> String,
+    pub too_many_tasks: fn(usize) -> String,
+    pub too_many_columns: fn(usize) -> String,
+    pub unsupported_export_version: fn(u32, u32) -> String,
+    pub already_estimated: &'static str,
+    pub no_key_or_title: &'static str,
+    pub title_too_long: fn(usize) -> String,
+    pub description_too_long: fn(usize) -> String,
+    pub queued_tasks: fn(usize) -> String,
+    pub rejected_rows: fn(usize) -> String,
+    pub rejected_row: fn(usize, &str) -> String,
+    pub queue_started: &'static str,
+
+    pub settings_menu: &'static str,
+    pub settings_saved: &'static str,
+    pub settings_done_button: &'static str,
+    pub config_usage: &'static str,
+    pub vote_mode_set: fn(&str) -> String,
+    pub spread_set: fn(usize) -> String,
+    pub spread_off: &'static str,
+    pub max_estimate_set: fn(usize) -> String,
+    pub max_estimate_off: &'static str,
+    pub min_voters_set: fn(usize) -> String,
+    pub min_voters_off: &'static str,
+    pub comments_on: &'static str,
+    pub comments_off: &'static str,
+    pub deck_setting: &'static str,
+    pub language_setting: &'static str,
+    pub reveal_setting: &'static str,
+    pub auto_reveal_setting: &'static str,
+    pub permissions_setting: &'static str,
+    pub timer_setting: &'static str,
+    pub anonymous_setting: &'static str,
+    pub off: &'static str,
+    pub yes: &'static str,
+    pub no: &'static str,
+    pub auto_reveal_value: fn(usize) -> String,
+    pub timer_value: fn(usize) -> String,
+    pub deck_fibonacci: &'static str,
+    pub deck_modified: &'static str,
+    pub deck_powers_of_two: &'static str,
+    pub language_auto: &'static str,
+    pub reveal_hidden: &'static str,
+    pub reveal_open: &'static str,
+    pub permissions_initiator: &'static str,
+    pub permissions_admins: &'static str,
+    pub permissions_everyone: &'static str,
+    pub vote_mode_single: &'static str,
+    pub vote_mode_range: &'static str,
+
+    pub no_stats: &'static str,
+    pub stats_heading: &'static str,
+    pub stats_line: fn(&str, usize, &str, usize) -> String,
+    pub deviation: fn(f64, &str) -> String,
+    pub deviation_unknown: &'static str,
+    pub overestimates: fn(f64) -> String,
+    pub underestimates: fn(f64) -> String,
+    pub no_bias: &'static str,
+}
+
+pub static RU: Texts = Texts {
+    help_header: "Команды бота:",
+    commands: [
+        ("help", "Вывести это сообщение"),
+        ("poker", "Начать оценку задачи"),
+        ("edit", "Изменить задачу сессии, ответом на её сообщение"),
+        (
+            "import",
+            "Импортировать задачи: CSV или JSON-выгрузка с подписью /import",
+        ),
+        ("next", "Начать следующую задачу из импортированной очереди"),
+        ("compare", "Сравнить задачи попарно, по одной на строке"),
+        ("history", "История сессий чата"),
+        ("active", "Незавершённые сессии чата"),
+        ("bump", "Переотправить сессию вниз чата"),
+        ("stats", "Статистика оценок участников"),
+        (
+            "export",
+            "Выгрузить сессии: /export [csv|json] [с ГГГГ-ММ-ДД] [по ГГГГ-ММ-ДД]",
+        ),
+        (
+            "config",
+            "Настройки чата, /config без параметров покажет список",
+        ),
+        ("settings", "Меню настроек чата"),
+    ],
+    command_not_found: "Команда не найдена.",
+    missing_task: "Отсутствует ссылка или ID задачи.",
+    admin_only: "Настройки чата может менять только администратор.",
+    pick_estimate_first: "Сначала выберите оценку.",
+    confidence_saved: "Уверенность сохранена.",
+    queue_empty: "Очередь задач пуста.",
+    unknown_session: "Сессия этого сообщения не найдена. Начните новую командой /poker.",
+    inline_feedback_required: "Сессия не сохранена: Telegram не сообщил боту об отправке. Владелец бота должен включить Inline Feedback в @BotFather.",
+    unknown_button: "Неизвестная кнопка.",
+    outdated_buttons: "Кнопки обновились, нажмите ещё раз.",
+
+    session_heading: "Оценка задачи",
+    initiator: "Инициатор",
+    estimates: "Оценки",
+    session_cancelled: "Сессия отменена.",
+    spread_too_big: "⚠️ Разброс слишком большой — обсудите и перезапустите.",
+    common_range: "Общий интервал",
+    no_common_range: "Общего интервала нет, обсудите крайние оценки.",
+    range_hint: "Диапазон: первая карта — нижняя граница, вторая — верхняя.",
+    anonymous_hint: "🕶 Анонимное голосование: имена рядом с оценками не показываются.",
+    timer_hint: |minutes| {
+        format!(
+            "⏱ Голосование завершится само через {} мин после начала раунда.",
+            minutes
+        )
+    },
+    auto_reveal_hint: |votes| format!("⚡️ Оценки откроются после {} голосов.", votes),
+    weighted_average: "Среднее с учётом уверенности",
+    low_confidence: |unsure, total| format!("Низкая уверенность ({} из {})", unsure, total),
+    confidence_buttons: ["Не уверен", "Средне", "Уверен"],
+    func_buttons: ["Перезапустить", "Завершить"],
+    new_round_button: "🔁 Обсудили, новый раунд",
+    next_session_button: "▶️ Следующая задача",
+    inline_title: |title| format!("Оценить: {}", title),
+    inline_description: "Отправить сообщение сессии с картами для голосования",
+
+    estimate_too_big: |max_estimate| {
+        format!(
+            "Оценка больше {}, подумайте о разбиении задачи.",
+            max_estimate
+        )
+    },
+    too_few_voters: |voters, min_voters| {
+        format!(
+            "Результат не засчитан: проголосовали {} из минимум {}.",
+            voters, min_voters
+        )
+    },
+    missing_comments: |names| {
+        format!(
+            "Ждём комментарии к крайним оценкам (ответом на это сообщение): {}.",
+            names
+        )
+    },
+
+    session_not_found: "Сессия не найдена.",
+    no_manage_rights: "У вас нет прав управлять этой сессией.",
+    session_finished: "Сессия уже завершена.",
+    no_active_sessions: "Незавершённых сессий нет.",
+    nothing_to_bump: "Нет сессии, которую можно переотправить. Ответьте командой на сообщение сессии.",
+    edit_reply_required: "Ответьте командой /edit на сообщение сессии.",
+    wizard_title_prompt: "Пришлите ссылку или название задачи ответом на это сообщение.",
+    wizard_description_prompt: "Пришлите описание задачи или «-», чтобы пропустить его.",
+    wizard_deck_prompt: "Выберите колоду:",
+    wizard_timer_prompt: "Выберите таймер:",
+    wizard_expired: "Этот диалог уже завершён, начните новый командой /poker.",
+    wizard_not_yours: "Отвечать может только тот, кто начал диалог.",
+    active_heading: "Незавершённые сессии",
+    finish_session_button: |title| format!("✅ Завершить «{}»", title),
+    cancel_session_button: "✖️ Отменить",
+    delete_session_button: "🗑 Удалить",
+
+    compare_usage: |max_items| {
+        format!(
+            "Укажите от 2 до {} задач, по одной на строке после /compare.",
+            max_items
+        )
+    },
+    unknown_comparison: "Сравнение этого сообщения не найдено. Начните новое командой /compare.",
+    comparison_finished: "Сравнение уже завершено.",
+    comparison_pair_passed: "Эта пара уже пройдена, ответьте на текущую.",
+    compare_initiator_only: "Переключать пары может только инициатор.",
+    compare_pair: |pair, pairs, left, right, answers| {
+        format!(
+            "Сравнение задач: пара {} из {}\n\n⬅️ {}\n➡️ {}\n\nКакая задача больше? Ответов: {}",
+            pair, pairs, left, right, answers
+        )
+    },
+    comparison_without_answers: "Сравнение завершено без ответов.",
+    ranking_heading: "Результат сравнения (от большей к меньшей, с предлагаемой оценкой):",
+    ranking_line: |place, item, card, rating| {
+        format!("{}. {} — {} (рейтинг {:.0})", place, item, card, rating)
+    },
+    compare_answer_buttons: ["⬅️ Левая больше", "Одинаково", "Правая больше ➡️"],
+    compare_func_buttons: ["Следующая пара", "Завершить"],
+
+    export_usage: "Использование: /export [csv|json] [с ГГГГ-ММ-ДД] [по ГГГГ-ММ-ДД]",
+    nothing_to_export: "Нет сессий для выгрузки.",
+
+    no_history: "В этом чате ещё не было сессий.",
+    history_heading: |page, pages| format!("История сессий, страница {} из {}", page, pages),
+    newer_button: "◀️ Новее",
+    older_button: "Старше ▶️",
+    median: |median| format!("медиана {}", median),
+    without_votes: "без оценок",
+    voting_in_progress: "идёт голосование",
+    votes_count: |votes| format!("голосов: {}", votes),
+    unknown_date: "дата неизвестна",
+
+    import_usage: "Отправьте CSV-файл со строками «ключ/ссылка, название, описание» или JSON-выгрузку /export с подписью /import или ответом на сообщение бота.",
+    import_not_allowed: "Импортировать задачи может только администратор чата.",
+    next_not_allowed: "Начать следующую задачу из очереди может только администратор чата.",
+    file_too_big: "Файл слишком большой, максимум 1 МБ.",
+    unreadable_file: |error| format!("Не удалось прочитать файл: {}", error),
+    too_many_tasks: |max_rows| format!("Слишком много задач, максимум {} за раз.", max_rows),
+    too_many_columns: |columns| format!("{} колонок вместо 3", columns),
+    unsupported_export_version: |version, expected| {
+        format!(
+            "неподдерживаемая версия выгрузки {}, ожидается {}",
+            version, expected
+        )
+    },
+    already_estimated: "сессия уже завершена",
+    no_key_or_title: "нет ни ключа, ни названия",
+    title_too_long: |max_length| format!("название длиннее {} символов", max_length),
+    description_too_long: |max_length| format!("описание длиннее {} символов", max_length),
+    queued_tasks: |tasks| format!("Задач в очереди: {}.", tasks),
+    rejected_rows: |rows| format!("Отклонено: {}", rows),
+    rejected_row: |row, reason| format!("строка {}: {}", row, reason),
+    queue_started: "Начинаем с первой, следующая — кнопкой после завершения или /next.",
+
+    settings_menu: "Настройки чата
+
+Нажмите на настройку, чтобы переключить её значение. Колода, таймер и анонимность применяются к новым сессиям.",
+    settings_saved: "Настройки чата сохранены.",
+    settings_done_button: "✅ Готово",
+    config_usage: "Использование:
+/config mode single|range — режим голосования
+/config spread N|off — максимальный разброс оценок в картах
+/config max N|off — оценка, после которой задачу стоит разбить
+/config voters N|off — минимум голосов для валидного результата
+/config comments on|off — комментарии от голосовавших за крайние оценки
+Остальные настройки — в меню /settings.",
+    vote_mode_set: |vote_mode| format!("Режим голосования для новых сессий: {}.", vote_mode),
+    spread_set: |max_spread| {
+        format!(
+            "Переголосование, если оценки расходятся больше чем на {} карт.",
+            max_spread
+        )
+    },
+    spread_off: "Проверка разброса оценок отключена.",
+    max_estimate_set: |max_estimate| {
+        format!(
+            "Задачи с оценкой больше {} будет предложено разбить.",
+            max_estimate
+        )
+    },
+    max_estimate_off: "Проверка максимальной оценки отключена.",
+    min_voters_set: |min_voters| {
+        format!(
+            "Результат засчитывается, если проголосовали хотя бы {}.",
+            min_voters
+        )
+    },
+    min_voters_off: "Минимальное число голосов не требуется.",
+    comments_on: "Голосовавшие за крайние оценки должны их прокомментировать ответом на сообщение сессии.",
+    comments_off: "Комментарии к крайним оценкам не требуются.",
+    deck_setting: "🃏 Колода",
+    language_setting: "🌐 Язык",
+    reveal_setting: "👁 Показ оценок",
+    auto_reveal_setting: "⚡️ Автовскрытие",
+    permissions_setting: "🔐 Управление сессией",
+    timer_setting: "⏱ Таймер",
+    anonymous_setting: "🕶 Анонимно",
+    off: "выкл",
+    yes: "да",
+    no: "нет",
+    auto_reveal_value: |votes| format!("после {} голосов", votes),
+    timer_value: |minutes| format!("{} мин", minutes),
+    deck_fibonacci: "Фибоначчи",
+    deck_modified: "модифицированный Фибоначчи",
+    deck_powers_of_two: "степени двойки",
+    language_auto: "как у инициатора",
+    reveal_hidden: "после завершения",
+    reveal_open: "сразу",
+    permissions_initiator: "инициатор",
+    permissions_admins: "инициатор и админы",
+    permissions_everyone: "все",
+    vote_mode_single: "одна карта",
+    vote_mode_range: "диапазон",
+
+    no_stats: "Статистики пока нет: в чате нет завершённых сессий с оценками.",
+    stats_heading: "Статистика оценок",
+    stats_line: |name, sessions, deviation, outliers| {
+        format!(
+            "{}\nсессий: {}, {}, крайняя оценка в {} из {}",
+            name, sessions, deviation, outliers, sessions
+        )
+    },
+    deviation: |average, tendency| format!("отклонение от медианы {:.0}%, {}", average, tendency),
+    deviation_unknown: "отклонение не посчитать",
+    overestimates: |tendency| format!("склонен завышать (+{:.0}%)", tendency),
+    underestimates: |tendency| format!("склонен занижать ({:.0}%)", tendency),
+    no_bias: "без заметного перекоса",
+};
+
+pub static EN: Texts = Texts {
+    help_header: "Bot commands:",
+    commands: [
+        ("help", "Show this message"),
+        ("poker", "Start estimating a task"),
+        ("edit", "Change the task of a session, as a reply to it"),
+        ("import", "Import tasks: a CSV or JSON export captioned /import"),
+        ("next", "Start the next task from the imported queue"),
+        ("compare", "Compare tasks in pairs, one per line"),
+        ("history", "Sessions of this chat"),
+        ("active", "Unfinished sessions of this chat"),
+        ("bump", "Repost a session at the bottom of the chat"),
+        ("stats", "Estimation statistics of the participants"),
+        (
+            "export",
+            "Export sessions: /export [csv|json] [from YYYY-MM-DD] [to YYYY-MM-DD]",
+        ),
+        ("config", "Chat settings, /config without arguments lists them"),
+        ("settings", "Chat settings menu"),
+    ],
+    command_not_found: "Command not found!",
+    missing_task: "The task link or ID is missing.",
+    admin_only: "Only administrators can change the chat settings.",
+    pick_estimate_first: "Pick an estimate first.",
+    confidence_saved: "Confidence saved.",
+    queue_empty: "The task queue is empty.",
+    unknown_session: "This message has no session behind it. Start a new one with /poker.",
+    inline_feedback_required: "The session was not saved since Telegram did not report the message to the bot. The bot owner has to enable inline feedback in @BotFather.",
+    unknown_button: "Unknown button.",
+    outdated_buttons: "The buttons were updated, please press again.",
+
+    session_heading: "Estimating",
+    initiator: "Initiator",
+    estimates: "Estimates",
+    session_cancelled: "Session cancelled.",
+    spread_too_big: "⚠️ The estimates are too far apart — discuss them and restart.",
+    common_range: "Common range",
+    no_common_range: "There is no common range, discuss the extreme estimates.",
+    range_hint: "Range: the first card is the lower bound, the second one the upper bound.",
+    anonymous_hint: "🕶 Anonymous voting: names are not shown next to the estimates.",
+    timer_hint: |minutes| {
+        format!(
+            "⏱ Voting finishes by itself {} min after the round starts.",
+            minutes
+        )
+    },
+    auto_reveal_hint: |votes| format!("⚡️ Estimates are revealed after {} votes.", votes),
+    weighted_average: "Average weighted by confidence",
+    low_confidence: |unsure, total| format!("Low confidence ({} of {})", unsure, total),
+    confidence_buttons: ["Unsure", "Fairly sure", "Sure"],
+    func_buttons: ["Restart", "Finish"],
+    new_round_button: "🔁 Discussed, new round",
+    next_session_button: "▶️ Next task",
+    inline_title: |title| format!("Estimate: {}", title),
+    inline_description: "Post a session message with the voting cards",
+
+    estimate_too_big: |max_estimate| {
+        format!(
+            "An estimate is above {}, consider splitting the task.",
+            max_estimate
+        )
+    },
+    too_few_voters: |voters, min_voters| {
+        format!(
+            "The result does not count: {} voted, at least {} needed.",
+            voters, min_voters
+        )
+    },
+    missing_comments: |names| {
+        format!(
+            "Waiting for comments on the extreme estimates (reply to this message): {}.",
+            names
+        )
+    },
+
+    session_not_found: "Session not found.",
+    no_manage_rights: "You are not allowed to manage this session.",
+    session_finished: "The session is already finished.",
+    no_active_sessions: "There are no unfinished sessions.",
+    nothing_to_bump: "There is no session to repost. Reply with the command to a session message.",
+    edit_reply_required: "Reply with /edit to a session message.",
+    wizard_title_prompt: "Send the task link or title in reply to this message.",
+    wizard_description_prompt: "Send the task description, or \"-\" to skip it.",
+    wizard_deck_prompt: "Pick the deck:",
+    wizard_timer_prompt: "Pick the timer:",
+    wizard_expired: "This dialogue is over, start a new one with /poker.",
+    wizard_not_yours: "Only the one who started the dialogue can answer.",
+    active_heading: "Unfinished sessions",
+    finish_session_button: |title| format!("✅ Finish “{}”", title),
+    cancel_session_button: "✖️ Cancel",
+    delete_session_button: "🗑 Delete",
+
+    compare_usage: |max_items| {
+        format!(
+            "List from 2 to {} tasks, one per line after /compare.",
+            max_items
+        )
+    },
+    unknown_comparison: "This message has no comparison behind it. Start a new one with /compare.",
+    comparison_finished: "The comparison is already finished.",
+    comparison_pair_passed: "This pair is already passed, answer the current one.",
+    compare_initiator_only: "Only the initiator can switch pairs.",
+    compare_pair: |pair, pairs, left, right, answers| {
+        format!(
+            "Comparing tasks: pair {} of {}\n\n⬅️ {}\n➡️ {}\n\nWhich task is bigger? Answers: {}",
+            pair, pairs, left, right, answers
+        )
+    },
+    comparison_without_answers: "The comparison finished without answers.",
+    ranking_heading: "Comparison result (biggest first, with a suggested estimate):",
+    ranking_line: |place, item, card, rating| {
+        format!("{}. {} — {} (rating {:.0})", place, item, card, rating)
+    },
+    compare_answer_buttons: ["⬅️ Left is bigger", "Same", "Right is bigger ➡️"],
+    compare_func_buttons: ["Next pair", "Finish"],
+
+    export_usage: "Usage: /export [csv|json] [from YYYY-MM-DD] [to YYYY-MM-DD]",
+    nothing_to_export: "There are no sessions to export.",
+
+    no_history: "There have been no sessions in this chat yet.",
+    history_heading: |page, pages| format!("Session history, page {} of {}", page, pages),
+    newer_button: "◀️ Newer",
+    older_button: "Older ▶️",
+    median: |median| format!("median {}", median),
+    without_votes: "no estimates",
+    voting_in_progress: "voting in progress",
+    votes_count: |votes| format!("votes: {}", votes),
+    unknown_date: "date unknown",
+
+    import_usage: "Send a CSV file with rows of key/url, title and description or a JSON file of /export with the /import caption or in reply to a message of the bot.",
+    import_not_allowed: "Only chat administrators can import tasks.",
+    next_not_allowed: "Only chat administrators can start the next queued task.",
+    file_too_big: "The file is too big, 1 MB at most.",
+    unreadable_file: |error| format!("Could not read the file: {}", error),
+    too_many_tasks: |max_rows| format!("Too many tasks, {} at most at a time.", max_rows),
+    too_many_columns: |columns| format!("{} columns instead of 3", columns),
+    unsupported_export_version: |version, expected| {
+        format!(
+            "unsupported export version {}, expected {}",
+            version, expected
+        )
+    },
+    already_estimated: "the session is already finished",
+    no_key_or_title: "neither a key nor a title",
+    title_too_long: |max_length| format!("the title is longer than {} characters", max_length),
+    description_too_long: |max_length| {
+        format!(
+            "the description is longer than {} characters",
+            max_length
+        )
+    },
+    queued_tasks: |tasks| format!("Tasks in the queue: {}.", tasks),
+    rejected_rows: |rows| format!("Rejected: {}", rows),
+    rejected_row: |row, reason| format!("row {}: {}", row, reason),
+    queue_started: "Starting with the first one, the next one comes with the button after finishing or with /next.",
+
+    settings_menu: "Chat settings
+
+Press a setting to switch it to the next value. The deck, the timer and anonymity apply to new sessions.",
+    settings_saved: "Chat settings saved.",
+    settings_done_button: "✅ Done",
+    config_usage: "Usage:
+/config mode single|range — voting mode
+/config spread N|off — maximum spread of the estimates, in cards
+/config max N|off — estimate above which a task should be split
+/config voters N|off — minimum number of votes for a valid result
+/config comments on|off — comments from the voters with extreme estimates
+Other settings are in the /settings menu.",
+    vote_mode_set: |vote_mode| format!("Voting mode for new sessions: {}.", vote_mode),
+    spread_set: |max_spread| {
+        format!(
+            "Estimates more than {} cards apart call for a new round.",
+            max_spread
+        )
+    },
+    spread_off: "The spread check is off.",
+    max_estimate_set: |max_estimate| {
+        format!(
+            "Tasks estimated above {} will be suggested for splitting.",
+            max_estimate
+        )
+    },
+    max_estimate_off: "The maximum estimate check is off.",
+    min_voters_set: |min_voters| {
+        format!(
+            "The result counts when at least {} people voted.",
+            min_voters
+        )
+    },
+    min_voters_off: "No minimum number of votes is required.",
+    comments_on: "Voters with extreme estimates have to explain them by replying to the session message.",
+    comments_off: "Comments on extreme estimates are not required.",
+    deck_setting: "🃏 Deck",
+    language_setting: "🌐 Language",
+    reveal_setting: "👁 Show estimates",
+    auto_reveal_setting: "⚡️ Auto-reveal",
+    permissions_setting: "🔐 Session control",
+    timer_setting: "⏱ Timer",
+    anonymous_setting: "🕶 Anonymous",
+    off: "off",
+    yes: "yes",
+    no: "no",
+    auto_reveal_value: |votes| format!("after {} votes", votes),
+    timer_value: |minutes| format!("{} min", minutes),
+    deck_fibonacci: "Fibonacci",
+    deck_modified: "modified Fibonacci",
+    deck_powers_of_two: "powers of two",
+    language_auto: "initiator's language",
+    reveal_hidden: "after finishing",
+    reveal_open: "right away",
+    permissions_initiator: "initiator",
+    permissions_admins: "initiator and admins",
+    permissions_everyone: "everyone",
+    vote_mode_single: "single card",
+    vote_mode_range: "range",
+
+    no_stats: "No statistics yet: the chat has no finished sessions with estimates.",
+    stats_heading: "Estimation statistics",
+    stats_line: |name, sessions, deviation, outliers| {
+        format!(
+            "{}\nsessions: {}, {}, extreme estimate in {} of {}",
+            name, sessions, deviation, outliers, sessions
+        )
+    },
+    deviation: |average, tendency| format!("deviation from the median {:.0}%, {}", average, tendency),
+    deviation_unknown: "deviation unknown",
+    overestimates: |tendency| format!("tends to overestimate (+{:.0}%)", tendency),
+    underestimates: |tendency| format!("tends to underestimate ({:.0}%)", tendency),
+    no_bias: "no noticeable bias",
+};
+
+pub const LANGUAGES: [Language; 2] = [Language::Ru, Language::En];
+
+pub fn texts(language: Language) -> &'static Texts {
+    match language {
+        Language::Ru => &RU,
+        Language::En => &EN,
+    }
+}
+
+/// The chat's language, or the user's one when the chat has none set. Russian
+/// stays the default for users whose client does not report a language.
+pub fn resolve(chat_language: Option<Language>, user: Option<&User>) -> Language {
+    chat_language.unwrap_or_else(
+        || match user.and_then(|user| user.language_code.as_deref()) {
+            Some(code) if !code.starts_with("ru") => Language::En,
+            _ => Language::Ru,
+        },
+    )
+}
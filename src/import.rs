@@ -0,0 +1,408 @@
+use crate::db::{self, QueuedSession};
+use crate::export::{ChatExport, SCHEMA_VERSION};
+use crate::helpers::{self, InTopic};
+use crate::i18n::{self, Texts};
+use std::error::Error;
+use teloxide::{net::Download, prelude::*, types::Me};
+use tokio_rusqlite::Connection;
+
+/// Larger files are rejected before downloading.
+const MAX_FILE_SIZE: u32 = 1024 * 1024;
+const MAX_ROWS: usize = 100;
+const MAX_TITLE_LENGTH: usize = 255;
+const MAX_DESCRIPTION_LENGTH: usize = 1000;
+/// Rejected rows listed in the report, the rest is only counted.
+const MAX_REPORTED_ROWS: usize = 10;
+
+/// A row that could not be queued, numbered from 1 like in spreadsheets.
+struct Rejection {
+    row: usize,
+    reason: String,
+}
+
+/// Tasks as session title and description, plus the rows that were rejected.
+type Parsed = (Vec<(String, String)>, Vec<Rejection>);
+
+/// A CSV or JSON document sent with an `/import` caption or in reply to a
+/// message of the bot. Other files shared in the chat are left alone.
+pub fn is_import_document(msg: &Message, me: &Me) -> bool {
+    let is_backlog = msg
+        .document()
+        .and_then(|document| document.file_name.as_deref())
+        .is_some_and(|name| {
+            let name = name.to_lowercase();
+            name.ends_with(".csv") || name.ends_with(".json")
+        });
+
+    let has_command = msg
+        .caption()
+        .and_then(|caption| caption.split_whitespace().next())
+        .is_some_and(|command| {
+            command == "/import" || command == format!("/import@{}", me.username())
+        });
+
+    let replies_to_bot = msg
+        .reply_to_message()
+        .and_then(|reply| reply.from())
+        .is_some_and(|user| user.id == me.id);
+
+    is_backlog && (has_command || replies_to_bot)
+}
+
+pub async fn document_handler(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = msg.chat.id;
+    let config = conn
+        .call(move |conn| db::find_chat_config(conn, chat_id))
+        .await?;
+    let language = i18n::resolve(config.language, msg.from());
+    let texts = i18n::texts(language);
+    let thread_id = helpers::topic_id(&msg);
+
+    if !crate::can_manage_queue(&bot, &msg, &config).await? {
+        bot.send_message(chat_id, texts.import_not_allowed)
+            .in_topic(thread_id)
+            .await?;
+        return Ok(());
+    }
+
+    let document = msg.document().unwrap();
+    let is_json = document
+        .file_name
+        .as_deref()
+        .is_some_and(|name| name.to_lowercase().ends_with(".json"));
+
+    if document.file.size > MAX_FILE_SIZE {
+        bot.send_message(chat_id, texts.file_too_big)
+            .in_topic(thread_id)
+            .await?;
+        return Ok(());
+    }
+
+    let file = bot.get_file(&document.file.id).await?;
+    let mut content = Vec::new();
+    bot.download_file(&file.path, &mut content).await?;
+
+    let parsed = if is_json {
+        parse_json_export(&content, texts).map_err(|error| error.to_string())
+    } else {
+        parse_csv(&content, texts).map_err(|error| error.to_string())
+    };
+
+    let (tasks, rejections) = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            bot.send_message(chat_id, (texts.unreadable_file)(&error))
+                .in_topic(thread_id)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if tasks.len() > MAX_ROWS {
+        bot.send_message(chat_id, (texts.too_many_tasks)(MAX_ROWS))
+            .in_topic(thread_id)
+            .await?;
+        return Ok(());
+    }
+
+    let user = msg.from().unwrap().clone();
+    let initiator_db_id = conn.call(move |conn| db::upsert_user(conn, &user)).await?;
+
+    let queued: Vec<QueuedSession> = tasks
+        .into_iter()
+        .map(|(title, description)| QueuedSession {
+            title,
+            description,
+            initiator_db_id,
+        })
+        .collect();
+
+    let imported = conn
+        .call(move |conn| db::save_queued_sessions(conn, chat_id, queued))
+        .await?;
+
+    bot.send_message(chat_id, render_report(imported, &rejections, texts))
+        .in_topic(thread_id)
+        .await?;
+
+    if imported > 0 {
+        crate::start_next_queued_session(&bot, &conn, chat_id, thread_id, language).await?;
+    }
+
+    Ok(())
+}
+
+/// Rows of `key/url, title, description`. The header row is optional, the
+/// title and the description may be omitted.
+fn parse_csv(content: &[u8], texts: &Texts) -> Result<Parsed, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content);
+
+    let mut tasks = Vec::new();
+    let mut rejections = Vec::new();
+
+    for (idx, record) in reader.records().enumerate() {
+        let record = record?;
+        let row = idx + 1;
+
+        let key = record.get(0).unwrap_or_default();
+        let title = record.get(1).unwrap_or_default();
+        let description = record.get(2).unwrap_or_default();
+
+        if row == 1 && matches!(key.to_lowercase().as_str(), "key" | "url" | "key/url") {
+            continue;
+        }
+
+        let task = if record.len() > 3 {
+            Err((texts.too_many_columns)(record.len()))
+        } else {
+            to_task(key, title, description, texts)
+        };
+
+        match task {
+            Ok(task) => tasks.push(task),
+            Err(reason) => rejections.push(Rejection { row, reason }),
+        }
+    }
+
+    Ok((tasks, rejections))
+}
+
+/// Open sessions of a JSON export made by `/export json`, finished ones are
+/// skipped since they are already estimated.
+fn parse_json_export(content: &[u8], texts: &Texts) -> Result<Parsed, serde_json::Error> {
+    let export: ChatExport = serde_json::from_slice(content)?;
+
+    if export.schema_version != SCHEMA_VERSION {
+        return Ok((
+            Vec::new(),
+            vec![Rejection {
+                row: 0,
+                reason: (texts.unsupported_export_version)(export.schema_version, SCHEMA_VERSION),
+            }],
+        ));
+    }
+
+    let mut tasks = Vec::new();
+    let mut rejections = Vec::new();
+
+    for (idx, session) in export.sessions.iter().enumerate() {
+        let task = if session.status == "open" {
+            to_task(&session.title, "", &session.description, texts)
+        } else {
+            Err(texts.already_estimated.to_string())
+        };
+
+        match task {
+            Ok(task) => tasks.push(task),
+            Err(reason) => rejections.push(Rejection {
+                row: idx + 1,
+                reason,
+            }),
+        }
+    }
+
+    Ok((tasks, rejections))
+}
+
+/// Session title and description for a task. The key or link becomes the
+/// title so issue ids are still recognised, the task title moves into the
+/// description.
+fn to_task(
+    key: &str,
+    title: &str,
+    description: &str,
+    texts: &Texts,
+) -> Result<(String, String), String> {
+    let (session_title, session_description) = match (key.is_empty(), title.is_empty()) {
+        (true, true) => return Err(texts.no_key_or_title.to_string()),
+        (true, false) => (title.to_string(), description.to_string()),
+        (false, true) => (key.to_string(), description.to_string()),
+        (false, false) if description.is_empty() => (key.to_string(), title.to_string()),
+        (false, false) => (key.to_string(), format!("{}\n{}", title, description)),
+    };
+
+    if session_title.chars().count() > MAX_TITLE_LENGTH {
+        return Err((texts.title_too_long)(MAX_TITLE_LENGTH));
+    }
+
+    if session_description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        return Err((texts.description_too_long)(MAX_DESCRIPTION_LENGTH));
+    }
+
+    Ok((session_title, session_description))
+}
+
+fn render_report(imported: usize, rejections: &[Rejection], texts: &Texts) -> String {
+    let mut report = (texts.queued_tasks)(imported);
+
+    if !rejections.is_empty() {
+        let lines: Vec<String> = rejections
+            .iter()
+            .take(MAX_REPORTED_ROWS)
+            .map(|rejection| match rejection.row {
+                0 => rejection.reason.clone(),
+                row => (texts.rejected_row)(row, &rejection.reason),
+            })
+            .collect();
+
+        report.push_str(&format!(
+            "\n{}\n{}",
+            (texts.rejected_rows)(rejections.len()),
+            lines.join("\n")
+        ));
+
+        if rejections.len() > MAX_REPORTED_ROWS {
+            report.push_str("\n…");
+        }
+    }
+
+    if imported > 0 {
+        report.push_str(&format!("\n{}", texts.queue_started));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Language;
+    use serde_json::json;
+
+    fn texts() -> &'static Texts {
+        i18n::texts(Language::En)
+    }
+
+    fn reasons(rejections: &[Rejection]) -> Vec<(usize, &str)> {
+        rejections
+            .iter()
+            .map(|rejection| (rejection.row, rejection.reason.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn skips_the_header_row() {
+        let (tasks, rejections) =
+            parse_csv(b"Key,Title,Description\nPROJ-1,Login,Via SSO", texts()).unwrap();
+
+        assert_eq!(
+            tasks,
+            vec![("PROJ-1".to_string(), "Login\nVia SSO".to_string())]
+        );
+        assert!(rejections.is_empty());
+    }
+
+    #[test]
+    fn header_is_only_looked_for_in_the_first_row() {
+        let (tasks, _) = parse_csv(b"PROJ-1,Login\nurl,Logout", texts()).unwrap();
+
+        assert_eq!(
+            tasks,
+            vec![
+                ("PROJ-1".to_string(), "Login".to_string()),
+                ("url".to_string(), "Logout".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_rows_with_too_many_columns() {
+        let (tasks, rejections) = parse_csv(b"PROJ-1,Login\na,b,c,d", texts()).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(reasons(&rejections), vec![(2, "4 columns instead of 3")]);
+    }
+
+    #[test]
+    fn rejects_rows_without_key_and_title() {
+        let (tasks, rejections) = parse_csv(b",,Only a description\n,Login", texts()).unwrap();
+
+        assert_eq!(tasks, vec![("Login".to_string(), String::new())]);
+        assert_eq!(reasons(&rejections), vec![(1, texts().no_key_or_title)]);
+    }
+
+    #[test]
+    fn limits_title_and_description_length() {
+        let key = "K".repeat(MAX_TITLE_LENGTH);
+        let description = "D".repeat(MAX_DESCRIPTION_LENGTH);
+
+        assert!(to_task(&key, "", &description, texts()).is_ok());
+        assert_eq!(
+            to_task(&format!("{}K", key), "", "", texts()),
+            Err((texts().title_too_long)(MAX_TITLE_LENGTH))
+        );
+        assert_eq!(
+            to_task(&key, "", &format!("{}D", description), texts()),
+            Err((texts().description_too_long)(MAX_DESCRIPTION_LENGTH))
+        );
+    }
+
+    fn export(schema_version: u32, statuses: &[&str]) -> Vec<u8> {
+        let sessions: Vec<_> = statuses
+            .iter()
+            .enumerate()
+            .map(|(idx, status)| {
+                json!({
+                    "id": idx + 1,
+                    "title": format!("Task {}", idx + 1),
+                    "description": "",
+                    "status": status,
+                    "vote_mode": "single",
+                    "initiator_tg_id": 1,
+                    "created_at": null,
+                    "finished_at": null,
+                    "final_estimate": null,
+                    "rounds": [],
+                })
+            })
+            .collect();
+
+        serde_json::to_vec(&json!({
+            "schema_version": schema_version,
+            "chat_id": -100123,
+            "exported_at": "2026-10-18T12:00:00Z",
+            "config": {
+                "vote_mode": "single",
+                "max_spread": null,
+                "max_estimate": null,
+                "min_voters": null,
+                "outlier_comments": false,
+            },
+            "users": [],
+            "sessions": sessions,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn imports_only_open_sessions_of_an_export() {
+        let (tasks, rejections) =
+            parse_json_export(&export(SCHEMA_VERSION, &["finished", "open"]), texts()).unwrap();
+
+        assert_eq!(tasks, vec![("Task 2".to_string(), String::new())]);
+        assert_eq!(reasons(&rejections), vec![(1, texts().already_estimated)]);
+    }
+
+    #[test]
+    fn rejects_exports_of_another_version() {
+        let (tasks, rejections) =
+            parse_json_export(&export(SCHEMA_VERSION + 1, &["open"]), texts()).unwrap();
+
+        assert!(tasks.is_empty());
+        assert_eq!(
+            reasons(&rejections),
+            vec![(
+                0,
+                (texts().unsupported_export_version)(SCHEMA_VERSION + 1, SCHEMA_VERSION).as_str()
+            )]
+        );
+    }
+}
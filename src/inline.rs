@@ -0,0 +1,136 @@
+use crate::db::{self, ChatConfig, DbUser, SessionMessage};
+use crate::helpers;
+use crate::i18n;
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{
+        ChosenInlineResult, InlineQueryResult, InlineQueryResultArticle, InputMessageContent,
+        InputMessageContentText, ParseMode,
+    },
+};
+use tokio_rusqlite::Connection;
+
+const RESULT_ID: &str = "poker";
+
+/// Offers the session message for `@bot ISSUE-123 description`. The session
+/// itself is saved once the result is chosen, since only then the inline
+/// message gets its id.
+pub async fn query_handler(bot: Bot, q: InlineQuery) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some((title, description)) = parse_query(&q.query) else {
+        bot.answer_inline_query(q.id, Vec::<InlineQueryResult>::new())
+            .await?;
+
+        return Ok(());
+    };
+
+    let language = i18n::resolve(None, Some(&q.from));
+    let texts = i18n::texts(language);
+    let config = ChatConfig::default();
+
+    let initiator = DbUser {
+        first_name: q.from.first_name.clone(),
+        last_name: q.from.last_name.clone().unwrap_or_default(),
+        username: q.from.username.clone().unwrap_or_default(),
+    };
+    let session = crate::new_session(
+        &config,
+        0,
+        initiator,
+        (title.clone(), description),
+        language,
+        SessionMessage::Inline(String::new()),
+        None,
+    );
+
+    let content = InputMessageContentText::new(crate::render_text(&session, &config, None))
+        .parse_mode(ParseMode::MarkdownV2);
+    let result = InlineQueryResultArticle::new(
+        RESULT_ID,
+        (texts.inline_title)(&title),
+        InputMessageContent::Text(content),
+    )
+    .description(texts.inline_description)
+    // Placeholder buttons until the chosen result brings the session id,
+    // pressing them before that asks for inline feedback to be enabled.
+    .reply_markup(crate::make_keyboard(&session, false, false, 0));
+
+    // The message names the initiator, so results must not be shared.
+    bot.answer_inline_query(q.id, vec![InlineQueryResult::Article(result)])
+        .cache_time(0)
+        .is_personal(true)
+        .await?;
+
+    Ok(())
+}
+
+/// Saves the session of a sent inline result and replaces the placeholder
+/// buttons of the preview with ones carrying the session id. Telegram only
+/// reports chosen results when inline feedback is enabled for the bot in
+/// @BotFather.
+pub async fn chosen_handler(
+    bot: Bot,
+    result: ChosenInlineResult,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (Some(inline_message_id), Some((title, description))) =
+        (result.inline_message_id, parse_query(&result.query))
+    else {
+        return Ok(());
+    };
+
+    let user = result.from;
+    let language = i18n::resolve(None, Some(&user));
+    let initiator = DbUser {
+        first_name: user.first_name.clone(),
+        last_name: user.last_name.clone().unwrap_or_default(),
+        username: user.username.clone().unwrap_or_default(),
+    };
+
+    let message = SessionMessage::Inline(inline_message_id.clone());
+    let session = conn
+        .call(move |conn| {
+            let initiator_db_id = db::upsert_user(conn, &user)?;
+
+            let session = crate::new_session(
+                &ChatConfig::default(),
+                initiator_db_id,
+                initiator,
+                (title, description),
+                language,
+                message,
+                None,
+            );
+            let session_id = db::save_session(conn, session.clone())?;
+
+            Ok(db::SessionWithInitiator {
+                session_id,
+                ..session
+            })
+        })
+        .await?;
+
+    bot.edit_message_reply_markup_inline(inline_message_id)
+        .reply_markup(crate::make_keyboard(&session, false, false, 0))
+        .await?;
+
+    Ok(())
+}
+
+/// Title and description of the task. A single line query is split after
+/// the first word, so `ISSUE-123 fix login` gets `fix login` as description.
+fn parse_query(query: &str) -> Option<(String, String)> {
+    let query = query.trim();
+
+    if query.is_empty() {
+        return None;
+    }
+
+    if query.contains('\n') {
+        return Some(helpers::parse_title_and_description(query));
+    }
+
+    let (title, description) = query.split_once(' ').unwrap_or((query, ""));
+
+    Some((title.to_string(), description.trim().to_string()))
+}
@@ -1,29 +1,64 @@
+mod active;
+mod bump;
+mod callback;
+mod compare;
 mod constants;
 mod db;
+mod edit;
+mod export;
+mod formatting;
 mod helpers;
+mod history;
+mod i18n;
+mod import;
+mod inline;
+mod policy;
+mod settings;
+mod stats;
+mod timer;
+mod wizard;
 
-use constants::{DEFAULT_SEQ, FUNC_BUTTONS};
-use db::{EstimationWithUser, SessionWithInitiator};
+use callback::{SessionAction, SessionCallback};
+use constants::{ADMIN_COMMANDS, CONFIDENCE_EMOJI};
+use db::{
+    ChatConfig, Confidence, DbUser, EstimationWithUser, Language, Permissions, RevealMode,
+    SessionMessage, SessionState, SessionWithInitiator, VoteMode,
+};
 use dotenv::dotenv;
+use helpers::InTopic;
 use rand::Rng;
-use rusqlite::Result;
+use rusqlite::{OptionalExtension, Result};
 use std::env;
 use std::error::Error;
 use teloxide::{
     prelude::*,
-    types::{Chat, InlineKeyboardButton, InlineKeyboardMarkup, Me, MessageId, User},
+    types::{
+        BotCommand, BotCommandScope, Chat, InlineKeyboardButton, InlineKeyboardMarkup, Me,
+        MessageId, UserId,
+    },
     utils::{command::BotCommands, markdown},
     RequestError,
 };
 use tokio_rusqlite::Connection;
 
-#[derive(BotCommands)]
-#[command(rename_rule = "lowercase", description = "Команды бота:")]
+/// Descriptions of the commands are localized in `i18n`, the derive only
+/// parses them.
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
 enum Command {
-    #[command(description = "Вывести это сообщение")]
     Help,
-    #[command(description = "Начать")]
     Poker(String),
+    Edit(String),
+    Import,
+    Next,
+    Compare(String),
+    History,
+    Active,
+    Bump,
+    Stats,
+    Export(String),
+    Config(String),
+    Settings,
 }
 
 #[tokio::main]
@@ -45,13 +80,96 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let bot = Bot::from_env();
 
+    if let Err(error) = register_commands(&bot).await {
+        log::error!("Failed to register the bot commands: {}", error);
+    }
+
+    tokio::spawn(timer::run(bot.clone(), conn.clone()));
+
     let handler = dptree::entry()
         .branch(
             Update::filter_message()
                 .filter_command::<Command>()
                 .endpoint(message_handler),
         )
-        .branch(Update::filter_callback_query().endpoint(callback_handler));
+        .branch(
+            Update::filter_edited_message()
+                .filter_command::<Command>()
+                .endpoint(edited_command_handler),
+        )
+        .branch(
+            Update::filter_message()
+                .filter(|msg: Message, me: Me| import::is_import_document(&msg, &me))
+                .endpoint(import::document_handler),
+        )
+        .branch(
+            Update::filter_message()
+                .filter_async(wizard::is_dialogue_reply)
+                .endpoint(wizard::reply_handler),
+        )
+        .branch(
+            Update::filter_message()
+                .filter(|msg: Message, me: Me| {
+                    msg.text().is_some_and(|text| !text.starts_with('/'))
+                        && msg
+                            .reply_to_message()
+                            .and_then(|reply| reply.from())
+                            .is_some_and(|author| author.id == me.id)
+                })
+                .endpoint(comment_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(|q: CallbackQuery| {
+                    q.data
+                        .as_deref()
+                        .and_then(SessionCallback::decode)
+                        .is_some_and(|callback| {
+                            matches!(callback.action, SessionAction::Confidence(_))
+                        })
+                })
+                .endpoint(confidence_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(|q: CallbackQuery| {
+                    q.data
+                        .as_deref()
+                        .and_then(SessionCallback::decode)
+                        .is_some()
+                })
+                .endpoint(callback_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(|q: CallbackQuery| q.data.as_deref().is_some_and(compare::is_compare_data))
+                .endpoint(compare::callback_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(|q: CallbackQuery| q.data.as_deref().is_some_and(history::is_history_data))
+                .endpoint(history::callback_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(|q: CallbackQuery| q.data.as_deref().is_some_and(active::is_active_data))
+                .endpoint(active::callback_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(|q: CallbackQuery| {
+                    q.data.as_deref().is_some_and(settings::is_settings_data)
+                })
+                .endpoint(settings::callback_handler),
+        )
+        .branch(
+            Update::filter_callback_query()
+                .filter(|q: CallbackQuery| q.data.as_deref().is_some_and(wizard::is_wizard_data))
+                .endpoint(wizard::callback_handler),
+        )
+        .branch(Update::filter_callback_query().endpoint(unknown_callback_handler))
+        .branch(Update::filter_inline_query().endpoint(inline::query_handler))
+        .branch(Update::filter_chosen_inline_result().endpoint(inline::chosen_handler));
 
     Dispatcher::builder(bot, handler)
         .dependencies(dptree::deps![conn])
@@ -63,6 +181,46 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Fills the "/" menu of Telegram clients. Users whose language has no list
+/// of its own get the English one, like the replies of the bot.
+async fn register_commands(bot: &Bot) -> Result<(), RequestError> {
+    let languages = i18n::LANGUAGES
+        .iter()
+        .map(|language| (Some(language.as_str()), i18n::texts(*language)))
+        .chain([(None, i18n::texts(Language::En))]);
+
+    for (language_code, texts) in languages {
+        let all: Vec<BotCommand> = texts
+            .commands
+            .iter()
+            .map(|(command, description)| BotCommand::new(*command, *description))
+            .collect();
+        let members: Vec<BotCommand> = all
+            .iter()
+            .filter(|command| !ADMIN_COMMANDS.contains(&command.command.as_str()))
+            .cloned()
+            .collect();
+
+        // Everybody administers their private chat with the bot.
+        for (scope, commands) in [
+            (BotCommandScope::Default, &members),
+            (BotCommandScope::AllGroupChats, &members),
+            (BotCommandScope::AllPrivateChats, &all),
+            (BotCommandScope::AllChatAdministrators, &all),
+        ] {
+            let mut request = bot.set_my_commands(commands.clone()).scope(scope);
+
+            if let Some(language_code) = language_code {
+                request = request.language_code(language_code);
+            }
+
+            request.await?;
+        }
+    }
+
+    Ok(())
+}
+
 async fn message_handler(
     bot: Bot,
     msg: Message,
@@ -70,85 +228,150 @@ async fn message_handler(
     conn: Connection,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     if let Some(text) = msg.text() {
+        let chat_id = msg.chat.id;
+        let config = conn
+            .call(move |conn| db::find_chat_config(conn, chat_id))
+            .await?;
+        let language = i18n::resolve(config.language, msg.from());
+        let texts = i18n::texts(language);
+        let thread_id = helpers::topic_id(&msg);
+
         match BotCommands::parse(text, me.username()) {
             Ok(Command::Help) => {
-                bot.send_message(msg.chat.id, Command::descriptions().to_string())
+                bot.send_message(msg.chat.id, render_help(texts))
+                    .in_topic(thread_id)
                     .await?;
             }
             Ok(Command::Poker(payload)) => {
-                if payload.is_empty() {
-                    bot.send_message(msg.chat.id, "Отсутствует ссылка или ID задачи.")
-                        .await?;
+                // An empty /poker in reply to a message estimates that message.
+                let task = if payload.is_empty() {
+                    msg.reply_to_message()
+                        .and_then(|reply| reply.text().or(reply.caption()))
+                        .and_then(helpers::task_from_message)
                 } else {
-                    let User {
-                        id: user_id,
-                        first_name,
-                        last_name,
-                        username,
-                        ..
-                    } = msg.from().unwrap().clone();
+                    Some(helpers::parse_title_and_description(&payload))
+                };
 
-                    let (title, description) = helpers::parse_title_and_description(&payload);
+                if let Some(task) = task {
+                    let user = msg.from().unwrap().clone();
+                    let initiator = DbUser {
+                        first_name: user.first_name.clone(),
+                        last_name: user.last_name.clone().unwrap_or_default(),
+                        username: user.username.clone().unwrap_or_default(),
+                    };
 
-                    let user_db_result = conn
+                    let chat_id = msg.chat.id;
+                    let (config, db_user_id) = conn
                         .call(move |conn| {
-                            db::find_or_insert_user(
-                                conn,
-                                user_id,
-                                first_name,
-                                last_name.unwrap_or("".to_string()),
-                                username.unwrap_or("".to_string()),
-                            )
+                            Ok((
+                                db::find_chat_config(conn, chat_id)?,
+                                db::upsert_user(conn, &user)?,
+                            ))
                         })
-                        .await;
+                        .await?;
 
-                    let title_for_db = title.clone();
-                    let description_for_db = description.clone();
-
-                    match user_db_result {
-                        Ok(db_user_id) => {
-                            conn.call(move |conn| {
-                                db::save_session(
-                                    conn,
-                                    msg.chat.id,
-                                    msg.id.0 + 1,
-                                    title_for_db,
-                                    description_for_db,
-                                    db_user_id.to_string(),
-                                )
-                            })
-                            .await?;
-                        }
-                        Err(error) => {
-                            panic!("Error user db: {:#?}", error)
-                        }
+                    // Only a task typed after the command keeps its formatting,
+                    // the one of a replied message is rearranged around its link.
+                    let description_markdown = if payload.is_empty() {
+                        None
+                    } else {
+                        formatting::formatted_description(&msg)
+                    };
+                    let session = SessionWithInitiator {
+                        description_markdown,
+                        ..new_session(
+                            &config,
+                            db_user_id,
+                            initiator,
+                            task,
+                            language,
+                            SessionMessage::Chat(chat_id, MessageId(0)),
+                            thread_id,
+                        )
                     };
-                    let User {
-                        first_name,
-                        last_name,
-                        username,
-                        ..
-                    } = msg.from().unwrap().clone();
-
-                    let arg = render_text(
-                        &title,
-                        &description,
-                        &first_name,
-                        &last_name.unwrap_or("".to_string()),
-                        &username.unwrap_or("".to_string()),
-                        0,
-                        None,
-                    );
-
-                    bot.parse_mode(teloxide::types::ParseMode::MarkdownV2)
-                        .send_message(msg.chat.id, arg)
-                        .reply_markup(make_keyboard(0, false))
+                    let session_id = post_session(&bot, &conn, session, &config).await?;
+
+                    let command_message_id = msg.id;
+                    conn.call(move |conn| {
+                        db::bind_command_message(conn, session_id, command_message_id)
+                    })
+                    .await?;
+                } else {
+                    wizard::start(bot, msg, conn, language).await?;
+                }
+            }
+            Ok(Command::Edit(payload)) => {
+                edit::edit(bot, msg, conn, payload, texts).await?;
+            }
+            Ok(Command::Import) => {
+                // The file itself comes with the command as its caption.
+                bot.send_message(msg.chat.id, texts.import_usage)
+                    .in_topic(thread_id)
+                    .await?;
+            }
+            Ok(Command::Next) => {
+                if can_manage_queue(&bot, &msg, &config).await? {
+                    start_next_queued_session(&bot, &conn, msg.chat.id, thread_id, language)
+                        .await?;
+                } else {
+                    bot.send_message(msg.chat.id, texts.next_not_allowed)
+                        .in_topic(thread_id)
+                        .await?;
+                }
+            }
+            Ok(Command::Compare(payload)) => {
+                compare::start(bot, msg, conn, payload, language).await?;
+            }
+            Ok(Command::History) => {
+                history::show(bot, msg, conn, texts).await?;
+            }
+            Ok(Command::Active) => {
+                active::show(bot, msg, conn, texts).await?;
+            }
+            Ok(Command::Bump) => {
+                bump::bump(bot, msg, conn, texts).await?;
+            }
+            Ok(Command::Stats) => {
+                stats::show(bot, msg, conn, texts).await?;
+            }
+            Ok(Command::Export(payload)) => {
+                export::export(bot, msg, conn, payload, texts).await?;
+            }
+            Ok(Command::Config(payload)) => {
+                let reply = if !is_config_allowed(&bot, &msg).await? {
+                    texts.admin_only.to_string()
+                } else {
+                    let mut config = config;
+
+                    match settings::apply_config_command(&mut config, &payload, texts) {
+                        Some(reply) => {
+                            conn.call(move |conn| db::save_chat_config(conn, chat_id, config))
+                                .await?;
+
+                            reply
+                        }
+                        None => texts.config_usage.to_string(),
+                    }
+                };
+
+                bot.send_message(msg.chat.id, reply)
+                    .in_topic(thread_id)
+                    .await?;
+            }
+            Ok(Command::Settings) => {
+                if is_config_allowed(&bot, &msg).await? {
+                    settings::show(bot, msg, conn, texts).await?;
+                } else {
+                    bot.send_message(msg.chat.id, texts.admin_only)
+                        .in_topic(thread_id)
                         .await?;
                 }
             }
 
             Err(_) => {
-                bot.send_message(msg.chat.id, "Command not found!").await?;
+                bot.send_message(msg.chat.id, texts.command_not_found)
+                    .in_topic(thread_id)
+                    .await?;
             }
         }
     }
@@ -156,228 +379,877 @@ async fn message_handler(
     Ok(())
 }
 
+fn render_help(texts: &i18n::Texts) -> String {
+    let lines: Vec<String> = texts
+        .commands
+        .iter()
+        .map(|(command, description)| format!("/{} — {}", command, description))
+        .collect();
+
+    format!("{}\n\n{}", texts.help_header, lines.join("\n"))
+}
+
 async fn callback_handler(
     bot: Bot,
     q: CallbackQuery,
     conn: Connection,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    bot.answer_callback_query(q.id).await?;
+    let callback = q.data.as_deref().and_then(SessionCallback::decode).unwrap();
+    let Some(mut session) = find_callback_session(&bot, &conn, &q, callback).await? else {
+        return Ok(());
+    };
+
+    // Votes are answered once they are saved, so a refused one can say why.
+    let callback_id = q.id;
+    if !matches!(callback.action, SessionAction::Vote(_)) {
+        bot.answer_callback_query(callback_id.clone()).await?;
+    }
+
+    let from = q.from.clone();
+    let user_id = from.id;
+    let user = q.from;
+
+    let user_db_id = conn.call(move |conn| db::upsert_user(conn, &user)).await;
+
+    let session_id = session.session_id;
+    let chat_id = session.chat_id();
+
+    let config = conn
+        .call(move |conn| db::find_session_config(conn, chat_id))
+        .await?;
+
+    let _ = match callback.action {
+        SessionAction::Restart => {
+            if can_manage_session(&bot, &config, &session, user_id, user_db_id.unwrap()).await?
+                && conn
+                    .call(move |conn| db::restart_session(conn, session_id))
+                    .await?
+            {
+                session.state = SessionState::Open;
+
+                let _ = send_response_message(bot, &conn, &session, &config, None, true).await;
+            }
+
+            Ok::<(), rusqlite::Error>(())
+        }
+        SessionAction::Finish => {
+            if can_manage_session(&bot, &config, &session, user_id, user_db_id.unwrap()).await? {
+                let _ = finish_session(bot, &conn, &mut session, &config).await;
+            }
+
+            Ok(())
+        }
+        SessionAction::Next => {
+            // Finalizing first makes a second click on the button a no-op
+            // instead of starting one more session.
+            if can_manage_session(&bot, &config, &session, user_id, user_db_id.unwrap()).await?
+                && conn
+                    .call(move |conn| db::finalize_session(conn, session_id))
+                    .await?
+            {
+                session.state = SessionState::Finalized;
+
+                if let Some(chat_id) = chat_id {
+                    let language = i18n::resolve(config.language, Some(&from));
+                    let _ = start_next_queued_session(
+                        &bot,
+                        &conn,
+                        chat_id,
+                        session.thread_id,
+                        language,
+                    )
+                    .await;
+                }
+
+                let estimations: Vec<EstimationWithUser> = conn
+                    .call(move |conn| db::find_estimations(conn, session_id))
+                    .await?;
+
+                let _ =
+                    send_response_message(bot, &conn, &session, &config, Some(estimations), false)
+                        .await;
+            }
+
+            Ok(())
+        }
+        SessionAction::Vote(value) => {
+            let vote_mode = session.vote_mode;
+
+            let estimation_db_result = conn
+                .call(move |conn| match vote_mode {
+                    VoteMode::Single => db::insert_update_estimation(
+                        conn,
+                        user_db_id.unwrap(),
+                        session_id,
+                        value.to_string(),
+                    ),
+                    VoteMode::Range => db::insert_update_range_estimation(
+                        conn,
+                        user_db_id.unwrap(),
+                        session_id,
+                        value,
+                    ),
+                })
+                .await;
+
+            // Votes on sessions that are not open are refused. A repeated
+            // single vote does not change the hidden estimations, range votes
+            // do since pending ranges are marked.
+            if let Ok(None) = estimation_db_result {
+                bot.answer_callback_query(callback_id)
+                    .text(i18n::texts(session.language).session_finished)
+                    .show_alert(true)
+                    .await?;
+
+                return Ok(());
+            }
+
+            bot.answer_callback_query(callback_id).await?;
+
+            if let Ok(Some(count)) = estimation_db_result {
+                if count == 1
+                    && vote_mode == VoteMode::Single
+                    && config.reveal_mode == RevealMode::Hidden
+                {
+                    return Ok(());
+                }
+            }
+
+            let estimations: Vec<EstimationWithUser> = conn
+                .call(move |conn| db::find_estimations(conn, session_id))
+                .await?;
+
+            let complete_votes = estimations
+                .iter()
+                .filter(|vote| vote_mode == VoteMode::Single || vote.value_max.is_some())
+                .count();
+
+            if session.state == SessionState::Open
+                && config
+                    .auto_reveal
+                    .is_some_and(|auto_reveal| complete_votes >= auto_reveal)
+            {
+                let _ = finish_session(bot, &conn, &mut session, &config).await;
+            } else {
+                let _ =
+                    send_response_message(bot, &conn, &session, &config, Some(estimations), false)
+                        .await;
+            }
+
+            Ok(())
+        }
+        SessionAction::Cancel => {
+            if can_cancel_session(&bot, &session, user_id, user_db_id.unwrap()).await? {
+                let _ = cancel_session(bot, &conn, &mut session, &config).await;
+            }
+
+            Ok(())
+        }
+        SessionAction::Delete => {
+            if can_cancel_session(&bot, &session, user_id, user_db_id.unwrap()).await? {
+                let _ = delete_session(bot, &conn, &mut session, &config).await;
+            }
+
+            Ok(())
+        }
+        // Routed to `confidence_handler`.
+        SessionAction::Confidence(_) => Ok(()),
+    };
+
+    Ok(())
+}
+
+async fn confidence_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let callback = q.data.as_deref().and_then(SessionCallback::decode).unwrap();
+    let SessionAction::Confidence(confidence) = callback.action else {
+        return Ok(());
+    };
+    let Some(session) = find_callback_session(&bot, &conn, &q, callback).await? else {
+        return Ok(());
+    };
+
     let CallbackQuery {
-        data: button_value,
+        id: callback_id,
         from: user,
-        message,
         ..
     } = q;
-    let User {
-        id: user_id,
-        first_name,
-        last_name,
-        username,
-        ..
-    } = user;
 
-    let user_db_id = conn
+    let user_db_id = conn.call(move |conn| db::upsert_user(conn, &user)).await?;
+
+    let session_id = session.session_id;
+
+    let updated = conn
+        .call(move |conn| db::save_confidence(conn, user_db_id, session_id, confidence))
+        .await?;
+
+    let texts = i18n::texts(session.language);
+    let answer = bot.answer_callback_query(callback_id);
+
+    match updated {
+        None => {
+            answer.text(texts.session_finished).show_alert(true).await?;
+        }
+        Some(0) => {
+            answer
+                .text(texts.pick_estimate_first)
+                .show_alert(true)
+                .await?;
+        }
+        Some(_) => {
+            answer.text(texts.confidence_saved).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Posts a new session message and stores the session with the id of the
+/// sent message. Returns the id of the session.
+pub async fn start_session(
+    bot: &Bot,
+    conn: &Connection,
+    chat_id: ChatId,
+    thread_id: Option<i32>,
+    initiator_db_id: usize,
+    task: (String, String),
+    language: Language,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let (config, initiator) = conn
         .call(move |conn| {
-            db::find_or_insert_user(
-                conn,
-                user_id,
-                first_name,
-                last_name.unwrap_or("".to_string()),
-                username.unwrap_or("".to_string()),
-            )
+            Ok((
+                db::find_chat_config(conn, chat_id)?,
+                db::find_user(conn, initiator_db_id)?,
+            ))
         })
-        .await;
+        .await?;
 
-    let Message {
-        chat: Chat { id: chat_id, .. },
-        id: message_id,
-        ..
-    } = message.unwrap();
+    let session = new_session(
+        &config,
+        initiator_db_id,
+        initiator,
+        task,
+        language,
+        SessionMessage::Chat(chat_id, MessageId(0)),
+        thread_id,
+    );
+
+    post_session(bot, conn, session, &config).await
+}
+
+/// Posts a session built by `new_session` for a chat and saves it. Returns
+/// the id of the session.
+pub async fn post_session(
+    bot: &Bot,
+    conn: &Connection,
+    session: SessionWithInitiator,
+    config: &ChatConfig,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let Some(chat_id) = session.chat_id() else {
+        return Err("inline sessions are saved once their result is chosen".into());
+    };
+
+    // The cards are added once the session is saved, since their callback
+    // data carries the session id. A failed save leaves no dead buttons.
+    let sent = bot
+        .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+        .send_message(chat_id, render_text(&session, config, None))
+        .in_topic(session.thread_id)
+        .await?;
+
+    let session = SessionWithInitiator {
+        message: SessionMessage::Chat(chat_id, sent.id),
+        ..session
+    };
+    let unsaved = session.clone();
+    let session_id = conn
+        .call(move |conn| db::save_session(conn, unsaved))
+        .await?;
 
-    let SessionWithInitiator {
+    let session = SessionWithInitiator {
         session_id,
+        ..session
+    };
+    bot.edit_message_reply_markup(chat_id, sent.id)
+        .reply_markup(make_keyboard(&session, false, false, 0))
+        .await?;
+
+    Ok(session_id)
+}
+
+/// A session not saved yet for the task's title and description, with its
+/// settings copied from the chat configuration.
+pub fn new_session(
+    config: &ChatConfig,
+    initiator_db_id: usize,
+    initiator: DbUser,
+    (title, description): (String, String),
+    language: Language,
+    message: SessionMessage,
+    thread_id: Option<i32>,
+) -> SessionWithInitiator {
+    SessionWithInitiator {
+        session_id: 0,
         title,
         description,
-        finished,
-        initiator_first_name,
-        initiator_last_name,
-        initiator_username,
+        description_markdown: None,
+        state: SessionState::Open,
+        vote_mode: config.vote_mode,
+        deck: config.deck,
+        anonymous: config.anonymous,
+        timer_minutes: config.timer_minutes,
+        language,
+        initiator_first_name: initiator.first_name,
+        initiator_last_name: initiator.last_name,
+        initiator_username: initiator.username,
         initiator_db_id,
-        ..
-    } = conn
-        .call(move |conn| db::find_session_with_initiator(conn, chat_id, message_id))
+        message,
+        thread_id,
+    }
+}
+
+/// Starts the oldest session waiting in the chat's import queue.
+pub async fn start_next_queued_session(
+    bot: &Bot,
+    conn: &Connection,
+    chat_id: ChatId,
+    thread_id: Option<i32>,
+    language: Language,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let queued = conn
+        .call(move |conn| db::pop_queued_session(conn, chat_id))
         .await?;
 
-    let _ = match button_value {
-        Some(val) => match val.as_str() {
-            "restart" => {
-                conn.call(move |conn| db::restart_session(conn, session_id))
-                    .await?;
+    match queued {
+        Some(queued) => {
+            start_session(
+                bot,
+                conn,
+                chat_id,
+                thread_id,
+                queued.initiator_db_id,
+                (queued.title, queued.description),
+                language,
+            )
+            .await?;
+        }
+        None => {
+            bot.send_message(chat_id, i18n::texts(language).queue_empty)
+                .in_topic(thread_id)
+                .await?;
+        }
+    }
 
-                if initiator_db_id == user_db_id.unwrap() {
-                    let _ = send_response_message(
-                        bot,
-                        chat_id,
-                        message_id,
-                        &title,
-                        &description,
-                        &initiator_first_name,
-                        &initiator_last_name,
-                        &initiator_username,
-                        finished,
-                        None,
-                        true,
-                    )
-                    .await;
-                }
+    Ok(())
+}
 
-                Ok::<(), rusqlite::Error>(())
-            }
-            "finish" => {
-                conn.call(move |conn| db::finish_session(conn, session_id))
-                    .await?;
+/// Reveals the votes of the session in its message. Sessions that are no
+/// longer open, e.g. finished by a concurrent click, are left as they are.
+pub async fn finish_session(
+    bot: Bot,
+    conn: &Connection,
+    session: &mut SessionWithInitiator,
+    config: &ChatConfig,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let session_id = session.session_id;
 
-                let session_id_for_find = session_id.clone();
+    if !conn
+        .call(move |conn| db::finish_session(conn, session_id))
+        .await?
+    {
+        return Ok(());
+    }
 
-                let estimations: Vec<EstimationWithUser> = conn
-                    .call(move |conn| db::find_estimations(conn, session_id_for_find))
-                    .await?;
+    let estimations: Vec<EstimationWithUser> = conn
+        .call(move |conn| db::find_estimations(conn, session_id))
+        .await?;
 
-                if initiator_db_id == user_db_id.unwrap() {
-                    let _ = send_response_message(
-                        bot,
-                        chat_id,
-                        message_id,
-                        &title,
-                        &description,
-                        &initiator_first_name,
-                        &initiator_last_name,
-                        &initiator_username,
-                        1,
-                        Some(estimations),
-                        false,
-                    )
-                    .await;
-                }
+    session.state = SessionState::Revealed;
 
-                Ok(())
-            }
-            _ => {
-                let session_id_for_insert = session_id.clone();
-
-                let estimation_db_result = conn
-                    .call(move |conn| {
-                        db::insert_update_estimation(
-                            conn,
-                            user_db_id.unwrap(),
-                            session_id_for_insert,
-                            val,
-                        )
-                    })
-                    .await;
+    send_response_message(bot, conn, session, config, Some(estimations), false).await?;
 
-                if let Ok(count) = estimation_db_result {
-                    if count == 1 {
-                        return Ok(());
-                    }
-                }
+    Ok(())
+}
 
-                let session_id_for_find = session_id.clone();
+/// Marks the session cancelled and removes its keyboard.
+pub async fn cancel_session(
+    bot: Bot,
+    conn: &Connection,
+    session: &mut SessionWithInitiator,
+    config: &ChatConfig,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let session_id = session.session_id;
 
-                let estimations: Vec<EstimationWithUser> = conn
-                    .call(move |conn| db::find_estimations(conn, session_id_for_find))
-                    .await?;
+    if !conn
+        .call(move |conn| db::cancel_session(conn, session_id))
+        .await?
+    {
+        return Ok(());
+    }
 
-                let _ = send_response_message(
-                    bot,
-                    chat_id,
-                    message_id,
-                    &title,
-                    &description,
-                    &initiator_first_name,
-                    &initiator_last_name,
-                    &initiator_username,
-                    finished,
-                    Some(estimations),
-                    false,
-                )
-                .await;
+    session.state = SessionState::Cancelled;
 
-                Ok(())
-            }
-        },
-        None => Ok(()),
+    send_response_message(bot, conn, session, config, None, false).await?;
+
+    Ok(())
+}
+
+/// Edits of a `/poker` message update the task of the session it started.
+async fn edited_command_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: Command,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match cmd {
+        Command::Poker(payload) => edit::command_edited(bot, msg, conn, payload).await,
+        _ => Ok(()),
+    }
+}
+
+/// Deletes the session message and archives the session. Inline messages and
+/// messages Telegram no longer lets the bot delete, older than 48 hours, are
+/// cancelled instead.
+pub async fn delete_session(
+    bot: Bot,
+    conn: &Connection,
+    session: &mut SessionWithInitiator,
+    config: &ChatConfig,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let SessionMessage::Chat(chat_id, message_id) = session.message else {
+        return cancel_session(bot, conn, session, config).await;
+    };
+
+    if bot.delete_message(chat_id, message_id).await.is_err() {
+        return cancel_session(bot, conn, session, config).await;
+    }
+
+    let session_id = session.session_id;
+
+    if conn
+        .call(move |conn| db::archive_session(conn, session_id))
+        .await?
+    {
+        session.state = SessionState::Archived;
+    }
+
+    Ok(())
+}
+
+/// Stores a reply to a finished session as the explanation of the author's
+/// estimate and re-renders the results.
+async fn comment_handler(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = msg.chat.id;
+    let message_id = msg.reply_to_message().unwrap().id;
+    let comment: String = msg.text().unwrap().chars().take(200).collect();
+    let user = msg.from().unwrap().clone();
+
+    let session_message = SessionMessage::Chat(chat_id, message_id);
+
+    let Some(session) = conn
+        .call(move |conn| db::find_session_by_message(conn, session_message))
+        .await?
+    else {
+        return Ok(());
+    };
+
+    if !session.state.is_revealed() {
+        return Ok(());
+    }
+
+    let session_id = session.session_id;
+
+    let user_db_id = conn.call(move |conn| db::upsert_user(conn, &user)).await?;
+
+    let updated = conn
+        .call(move |conn| db::save_comment(conn, user_db_id, session_id, comment))
+        .await?;
+
+    if updated == 0 {
+        return Ok(());
+    }
+
+    let estimations = conn
+        .call(move |conn| db::find_estimations(conn, session_id))
+        .await?;
+    let config = conn
+        .call(move |conn| db::find_chat_config(conn, chat_id))
+        .await?;
+
+    send_response_message(bot, &conn, &session, &config, Some(estimations), false).await?;
+
+    Ok(())
+}
+
+/// The message of the pressed button, sent to a chat or through inline mode.
+fn callback_message(q: &CallbackQuery) -> SessionMessage {
+    match (&q.inline_message_id, &q.message) {
+        (Some(inline_message_id), _) => SessionMessage::Inline(inline_message_id.clone()),
+        (None, message) => {
+            let message = message.as_ref().unwrap();
+
+            SessionMessage::Chat(message.chat.id, message.id)
+        }
+    }
+}
+
+/// Session of the pressed button. It has to be bound to the message of the
+/// button, so a crafted payload cannot reach the session of another chat,
+/// and votes have to be cards of the session's deck.
+async fn find_callback_session(
+    bot: &Bot,
+    conn: &Connection,
+    q: &CallbackQuery,
+    callback: SessionCallback,
+) -> Result<Option<SessionWithInitiator>, Box<dyn Error + Send + Sync>> {
+    let session_message = callback_message(q);
+    let session_id = callback.session_id;
+
+    // Placeholder buttons of an inline result stay in place when Telegram
+    // never reports the chosen result, which it only does with inline
+    // feedback enabled.
+    if session_id == 0 && matches!(session_message, SessionMessage::Inline(_)) {
+        bot.answer_callback_query(q.id.clone())
+            .text(i18n::texts(i18n::resolve(None, Some(&q.from))).inline_feedback_required)
+            .show_alert(true)
+            .await?;
+
+        return Ok(None);
+    }
+
+    let session = conn
+        .call(move |conn| db::find_session_by_id(conn, session_id).optional())
+        .await?
+        .filter(|session| session.message == session_message);
+
+    let Some(session) = session else {
+        reject_unknown_session(bot, conn, q, session_message).await?;
+
+        return Ok(None);
     };
 
+    if let SessionAction::Vote(value) = callback.action {
+        if !session.deck.cards().contains(&value.to_string().as_str()) {
+            bot.answer_callback_query(q.id.clone())
+                .text(i18n::texts(session.language).unknown_button)
+                .show_alert(true)
+                .await?;
+
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(session))
+}
+
+/// Alerts about a button of a message without a session, e.g. when saving
+/// the session failed. Buttons of chat messages are removed, inline ones are
+/// kept since their session may still be on its way with the chosen result.
+async fn reject_unknown_session(
+    bot: &Bot,
+    conn: &Connection,
+    q: &CallbackQuery,
+    session_message: SessionMessage,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = q.message.as_ref().map(|message| message.chat.id);
+    let config = conn
+        .call(move |conn| db::find_session_config(conn, chat_id))
+        .await?;
+    let texts = i18n::texts(i18n::resolve(config.language, Some(&q.from)));
+
+    bot.answer_callback_query(q.id.clone())
+        .text(texts.unknown_session)
+        .show_alert(true)
+        .await?;
+
+    if let SessionMessage::Chat(chat_id, message_id) = session_message {
+        bot.edit_message_reply_markup(chat_id, message_id).await?;
+    }
+
     Ok(())
 }
 
+/// Buttons whose data is not understood, like the ones of messages sent
+/// before the current payload format. Session messages get their keyboard
+/// redrawn in the current format.
+async fn unknown_callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let session_message = callback_message(&q);
+    let lookup = session_message.clone();
+
+    let Some(session) = conn
+        .call(move |conn| db::find_session_by_message(conn, lookup))
+        .await?
+    else {
+        return reject_unknown_session(&bot, &conn, &q, session_message).await;
+    };
+
+    bot.answer_callback_query(q.id)
+        .text(i18n::texts(session.language).outdated_buttons)
+        .show_alert(true)
+        .await?;
+
+    let (chat_id, session_id) = (session.chat_id(), session.session_id);
+    let (config, estimations) = conn
+        .call(move |conn| {
+            Ok((
+                db::find_session_config(conn, chat_id)?,
+                db::find_estimations(conn, session_id)?,
+            ))
+        })
+        .await?;
+
+    send_response_message(bot, &conn, &session, &config, Some(estimations), false).await
+}
+
+async fn is_config_allowed(bot: &Bot, msg: &Message) -> Result<bool, RequestError> {
+    match msg.from() {
+        Some(user) => is_chat_admin(bot, &msg.chat, user.id).await,
+        None => Ok(false),
+    }
+}
+
+/// Importing tasks and starting the queued ones creates sessions on behalf
+/// of the chat, so like the settings it is left to administrators unless
+/// everyone may manage sessions.
+pub async fn can_manage_queue(
+    bot: &Bot,
+    msg: &Message,
+    config: &ChatConfig,
+) -> Result<bool, RequestError> {
+    match msg.from() {
+        Some(_) if config.permissions == Permissions::Everyone => Ok(true),
+        Some(user) => is_chat_admin(bot, &msg.chat, user.id).await,
+        None => Ok(false),
+    }
+}
+
+/// Everybody is an administrator of their private chat with the bot.
+pub async fn is_chat_admin(bot: &Bot, chat: &Chat, user_id: UserId) -> Result<bool, RequestError> {
+    if chat.is_private() {
+        return Ok(true);
+    }
+
+    Ok(bot.get_chat_member(chat.id, user_id).await?.is_privileged())
+}
+
+/// Whether the user may cancel or delete the session from its message: the
+/// initiator and the administrators of its chat, whatever the permissions.
+pub async fn can_cancel_session(
+    bot: &Bot,
+    session: &SessionWithInitiator,
+    user_id: UserId,
+    user_db_id: usize,
+) -> Result<bool, RequestError> {
+    if session.initiator_db_id == user_db_id {
+        return Ok(true);
+    }
+
+    match session.chat_id() {
+        Some(chat_id) => Ok(bot.get_chat_member(chat_id, user_id).await?.is_privileged()),
+        None => Ok(false),
+    }
+}
+
+/// Whether the user may finish, restart or cancel the session under the
+/// chat's permissions.
+pub async fn can_manage_session(
+    bot: &Bot,
+    config: &ChatConfig,
+    session: &SessionWithInitiator,
+    user_id: UserId,
+    user_db_id: usize,
+) -> Result<bool, RequestError> {
+    match config.permissions {
+        Permissions::Everyone => Ok(true),
+        _ if session.initiator_db_id == user_db_id => Ok(true),
+        // Administrators of the chat of an inline message are unknown.
+        Permissions::InitiatorAndAdmins => match session.chat_id() {
+            Some(chat_id) => Ok(bot.get_chat_member(chat_id, user_id).await?.is_privileged()),
+            None => Ok(false),
+        },
+        Permissions::Initiator => Ok(false),
+    }
+}
+
 pub async fn send_response_message(
     bot: Bot,
-    chat_id: ChatId,
-    message_id: MessageId,
-    title: &str,
-    description: &str,
-    initiator_first_name: &str,
-    initiator_last_name: &str,
-    initiator_username: &str,
-    finished: u8,
+    conn: &Connection,
+    session: &SessionWithInitiator,
+    config: &ChatConfig,
     estimations: Option<Vec<EstimationWithUser>>,
     is_restart: bool,
-) -> Result<Message, RequestError> {
-    bot.parse_mode(teloxide::types::ParseMode::MarkdownV2)
-        .edit_message_text(
-            chat_id,
-            message_id,
-            render_text(
-                &title,
-                &description,
-                initiator_first_name,
-                initiator_last_name,
-                initiator_username,
-                finished,
-                estimations,
-            ),
-        )
-        .reply_markup(make_keyboard(finished, is_restart))
-        .await
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let spread_exceeded = session.state == SessionState::Revealed
+        && estimations
+            .as_deref()
+            .is_some_and(|votes| policy::is_spread_exceeded(config, session.deck, votes));
+
+    let queued = match session.chat_id() {
+        Some(chat_id) if session.state == SessionState::Revealed => {
+            let session_id = session.session_id;
+
+            conn.call(move |conn| db::count_queue_after(conn, chat_id, session_id))
+                .await?
+        }
+        _ => 0,
+    };
+
+    let bot = bot.parse_mode(teloxide::types::ParseMode::MarkdownV2);
+    let text = render_text(session, config, estimations);
+    let keyboard = make_keyboard(session, is_restart, spread_exceeded, queued);
+
+    match &session.message {
+        SessionMessage::Chat(chat_id, message_id) => {
+            bot.edit_message_text(*chat_id, *message_id, text)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        SessionMessage::Inline(inline_message_id) => {
+            bot.edit_message_text_inline(inline_message_id, text)
+                .reply_markup(keyboard)
+                .await?;
+        }
+    }
+
+    Ok(())
 }
 
 pub fn render_text(
-    title: &str,
-    description: &str,
-    initiator_first_name: &str,
-    initiator_last_name: &str,
-    initiator_username: &str,
-    session_finished: u8,
+    session: &SessionWithInitiator,
+    config: &ChatConfig,
     votes: Option<Vec<EstimationWithUser>>,
 ) -> String {
-    let processed_title = if helpers::is_url_valid(&title) {
-        if let Some(issue_id) = helpers::extract_issue_id(&title) {
+    let texts = i18n::texts(session.language);
+    let title = session.title.as_str();
+    let state = session.state;
+    let is_range = session.vote_mode == VoteMode::Range;
+
+    let processed_title = if helpers::is_url_valid(title) {
+        if let Some(issue_id) = helpers::extract_issue_id(title) {
             markdown::link(title, markdown::escape(issue_id.as_str()).as_str())
         } else {
-            markdown::escape(format!("{}", title).as_str())
+            markdown::escape(title)
         }
     } else {
-        markdown::escape(format!("{}", title).as_str())
+        markdown::escape(title)
     };
 
-    let voted_users_section = if let Some(voters_vec) = votes {
+    let mut summary_lines: Vec<String> = Vec::new();
+
+    if let Some(voters_vec) = &votes {
+        if state.is_revealed() && !voters_vec.is_empty() {
+            if policy::is_spread_exceeded(config, session.deck, voters_vec) {
+                summary_lines.push(markdown::bold(
+                    markdown::escape(texts.spread_too_big).as_str(),
+                ));
+            }
+
+            if is_range {
+                let ranges: Vec<(usize, usize)> = voters_vec
+                    .iter()
+                    .map(|vote| (vote.value, vote.value_max.unwrap_or(vote.value)))
+                    .collect();
+
+                summary_lines.push(match helpers::ranges_overlap(&ranges) {
+                    Some((low, high)) => markdown::bold(
+                        markdown::escape(
+                            format!(
+                                "{}: {}",
+                                texts.common_range,
+                                helpers::format_range(low, high)
+                            )
+                            .as_str(),
+                        )
+                        .as_str(),
+                    ),
+                    None => markdown::escape(texts.no_common_range),
+                });
+            }
+
+            summary_lines.extend(render_confidence_summary(
+                voters_vec,
+                session.anonymous,
+                texts,
+            ));
+
+            summary_lines.extend(
+                policy::evaluate(config, voters_vec, session.anonymous)
+                    .iter()
+                    .map(|violation| {
+                        markdown::escape(format!("⚠️ {}", violation.describe(texts)).as_str())
+                    }),
+            );
+        }
+    }
+
+    let show_values = state.is_revealed()
+        || (state == SessionState::Open && config.reveal_mode == RevealMode::Open);
+    let show_names = !(session.anonymous && show_values);
+
+    let voted_users_section = if let Some(mut voters_vec) = votes {
+        // Without names the order of the votes must not give the voters away.
+        if !show_names {
+            voters_vec.sort_by_key(|vote| (vote.value, vote.value_max));
+        }
+
         voters_vec
             .iter()
             .map(|user| {
-                let vote_char = if session_finished == 1 {
-                    user.value.to_string()
+                let pending = if is_range && state == SessionState::Open && user.value_max.is_none()
+                {
+                    "…"
+                } else {
+                    ""
+                };
+
+                let vote_char = if show_values {
+                    let confidence_mark = match user.confidence {
+                        Some(confidence) => format!(" {}", confidence_emoji(confidence)),
+                        None => "".to_string(),
+                    };
+
+                    format!(
+                        "{}{}{}",
+                        helpers::format_range(user.value, user.value_max.unwrap_or(user.value)),
+                        pending,
+                        confidence_mark
+                    )
                 } else {
                     let idx = rand::thread_rng().gen_range(0..constants::EMOJI_SET.len());
-                    constants::EMOJI_SET[idx].to_string()
+
+                    format!("{}{}", constants::EMOJI_SET[idx], pending)
                 };
 
-                format!(
-                    "{} - {}\n",
-                    vote_char.as_str(),
-                    helpers::make_username_line(&user.first_name, &user.last_name, &user.username)
-                )
+                let comment = match &user.comment {
+                    Some(comment) if state.is_revealed() => format!(" — {}", comment),
+                    _ => "".to_string(),
+                };
+
+                if show_names {
+                    format!(
+                        "{} - {}{}\n",
+                        vote_char.as_str(),
+                        helpers::make_username_line(
+                            &user.first_name,
+                            &user.last_name,
+                            &user.username
+                        ),
+                        comment
+                    )
+                } else {
+                    format!("{}{}\n", vote_char.as_str(), comment)
+                }
             })
             .collect()
     } else {
@@ -385,67 +1257,219 @@ pub fn render_text(
     };
 
     format!(
-        "Оценка задачи: {}{}\n{}\n{}",
+        "{}: {}{}\n{}{}\n{}{}",
+        markdown::escape(texts.session_heading),
         processed_title,
-        if description.to_string().is_empty() {
-            "".to_string()
-        } else {
-            markdown::italic(markdown::escape(format!("\n{}", description).as_str()).as_str())
+        match &session.description_markdown {
+            Some(description) => format!("\n{}", description),
+            None if session.description.is_empty() => "".to_string(),
+            None => markdown::italic(
+                markdown::escape(format!("\n{}", session.description).as_str()).as_str(),
+            ),
         },
         markdown::escape(
             format!(
-                "\nИнициатор: {}\n",
+                "\n{}: {}\n",
+                texts.initiator,
                 helpers::make_username_line(
-                    &initiator_first_name.to_string(),
-                    &initiator_last_name.to_string(),
-                    &initiator_username.to_string(),
+                    &session.initiator_first_name,
+                    &session.initiator_last_name,
+                    &session.initiator_username,
                 )
                 .as_str()
             )
             .as_str()
         ),
-        if voted_users_section.is_empty() {
+        markdown::escape(render_hints(session, config).as_str()),
+        if state == SessionState::Cancelled {
+            markdown::bold(markdown::escape(texts.session_cancelled).as_str())
+        } else if voted_users_section.is_empty() {
             "".to_string()
         } else {
             format!(
-                "Оценки:\n\n{}",
+                "{}:\n\n{}",
+                markdown::escape(texts.estimates),
                 markdown::escape(voted_users_section.as_str()),
             )
-        }
+        },
+        if summary_lines.is_empty() {
+            "".to_string()
+        } else {
+            format!("\n{}", summary_lines.join("\n"))
+        },
     )
 }
 
-fn make_keyboard(finished: u8, is_restart: bool) -> InlineKeyboardMarkup {
+/// Explains how voting works in the session, one line per rule.
+fn render_hints(session: &SessionWithInitiator, config: &ChatConfig) -> String {
+    let texts = i18n::texts(session.language);
+    let mut hints = String::new();
+
+    if session.vote_mode == VoteMode::Range {
+        hints.push_str(format!("{}\n", texts.range_hint).as_str());
+    }
+
+    if session.anonymous {
+        hints.push_str(format!("{}\n", texts.anonymous_hint).as_str());
+    }
+
+    if session.state == SessionState::Open {
+        if let Some(minutes) = session.timer_minutes {
+            hints.push_str(format!("{}\n", (texts.timer_hint)(minutes)).as_str());
+        }
+
+        if let Some(auto_reveal) = config.auto_reveal {
+            hints.push_str(format!("{}\n", (texts.auto_reveal_hint)(auto_reveal)).as_str());
+        }
+    }
+
+    hints
+}
+
+/// Flags low-confidence votes and weights the average by confidence, so the
+/// facilitator sees where the uncertainty is. Anonymous sessions only get the
+/// number of unsure voters.
+fn render_confidence_summary(
+    votes: &[EstimationWithUser],
+    anonymous: bool,
+    texts: &i18n::Texts,
+) -> Vec<String> {
+    if votes.iter().all(|vote| vote.confidence.is_none()) {
+        return Vec::new();
+    }
+
+    let (weighted_sum, weights) = votes.iter().fold((0.0, 0.0), |(sum, weights), vote| {
+        let weight = vote.confidence.unwrap_or_default().weight();
+        let midpoint = (vote.value + vote.value_max.unwrap_or(vote.value)) as f64 / 2.0;
+
+        (sum + midpoint * weight, weights + weight)
+    });
+
+    let mut lines = vec![markdown::escape(
+        format!("{}: {:.1}", texts.weighted_average, weighted_sum / weights).as_str(),
+    )];
+
+    let low_confidence: Vec<String> = votes
+        .iter()
+        .filter(|vote| vote.confidence == Some(Confidence::Low))
+        .map(|vote| helpers::make_username_line(&vote.first_name, &vote.last_name, &vote.username))
+        .collect();
+
+    if !low_confidence.is_empty() {
+        let names = if anonymous {
+            "".to_string()
+        } else {
+            format!(": {}", low_confidence.join(", "))
+        };
+
+        lines.push(markdown::bold(
+            markdown::escape(
+                format!(
+                    "{} {}{}",
+                    confidence_emoji(Confidence::Low),
+                    (texts.low_confidence)(low_confidence.len(), votes.len()),
+                    names
+                )
+                .as_str(),
+            )
+            .as_str(),
+        ));
+    }
+
+    lines
+}
+
+fn confidence_emoji(confidence: Confidence) -> &'static str {
+    Confidence::ALL
+        .iter()
+        .position(|item| *item == confidence)
+        .map_or("", |idx| CONFIDENCE_EMOJI[idx])
+}
+
+pub fn make_keyboard(
+    session: &SessionWithInitiator,
+    is_restart: bool,
+    spread_exceeded: bool,
+    queued: usize,
+) -> InlineKeyboardMarkup {
+    let texts = i18n::texts(session.language);
+    let button = |label: String, action: SessionAction| {
+        InlineKeyboardButton::callback(
+            label,
+            SessionCallback::new(session.session_id, action).encode(),
+        )
+    };
     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = Vec::new();
 
-    if finished == 0 || is_restart {
-        for items in DEFAULT_SEQ.chunks(4) {
+    if !matches!(session.state, SessionState::Open | SessionState::Revealed) {
+        return InlineKeyboardMarkup::new(keyboard);
+    }
+
+    if session.state == SessionState::Open || is_restart {
+        for items in session.deck.cards().chunks(4) {
             let row = items
                 .iter()
-                .map(|item| InlineKeyboardButton::callback(item.to_owned(), item.to_owned()))
+                .map(|item| {
+                    // All decks consist of whole numbers.
+                    button(
+                        item.to_string(),
+                        SessionAction::Vote(item.parse().unwrap_or_default()),
+                    )
+                })
                 .collect();
 
             keyboard.push(row);
         }
 
         keyboard.push(
-            FUNC_BUTTONS
+            Confidence::ALL
                 .iter()
-                .map(|(label, data)| {
-                    InlineKeyboardButton::callback(label.to_owned(), data.to_owned())
+                .zip(CONFIDENCE_EMOJI)
+                .zip(texts.confidence_buttons)
+                .map(|((confidence, emoji), label)| {
+                    button(
+                        format!("{} {}", emoji, label),
+                        SessionAction::Confidence(*confidence),
+                    )
                 })
                 .collect(),
         );
+
+        keyboard.push(vec![
+            button(texts.func_buttons[0].to_string(), SessionAction::Restart),
+            button(texts.func_buttons[1].to_string(), SessionAction::Finish),
+        ]);
+
+        // Inline messages cannot be deleted by the bot.
+        let mut cancel_row = vec![button(
+            texts.cancel_session_button.to_string(),
+            SessionAction::Cancel,
+        )];
+        if session.chat_id().is_some() {
+            cancel_row.push(button(
+                texts.delete_session_button.to_string(),
+                SessionAction::Delete,
+            ));
+        }
+        keyboard.push(cancel_row);
+    } else if spread_exceeded {
+        keyboard.push(vec![button(
+            texts.new_round_button.to_string(),
+            SessionAction::Restart,
+        )]);
+    } else if queued > 0 {
+        keyboard.push(vec![
+            button(texts.func_buttons[0].to_string(), SessionAction::Restart),
+            button(
+                format!("{} ({})", texts.next_session_button, queued),
+                SessionAction::Next,
+            ),
+        ]);
     } else {
-        keyboard.push(
-            FUNC_BUTTONS
-                .iter()
-                .filter(|(_, data)| data.to_owned() == "restart")
-                .map(|(label, data)| {
-                    InlineKeyboardButton::callback(label.to_owned(), data.to_owned())
-                })
-                .collect(),
-        )
+        keyboard.push(vec![button(
+            texts.func_buttons[0].to_string(),
+            SessionAction::Restart,
+        )]);
     }
 
     InlineKeyboardMarkup::new(keyboard)
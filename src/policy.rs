@@ -0,0 +1,229 @@
+use crate::db::{ChatConfig, Deck, EstimationWithUser};
+use crate::helpers;
+use crate::i18n::Texts;
+
+/// A chat rule broken by the revealed votes.
+#[derive(Debug)]
+pub enum Violation {
+    EstimateTooBig { max_estimate: usize },
+    TooFewVoters { min_voters: usize, voters: usize },
+    MissingComments(Vec<String>),
+}
+
+impl Violation {
+    pub fn describe(&self, texts: &Texts) -> String {
+        match self {
+            Violation::EstimateTooBig { max_estimate } => (texts.estimate_too_big)(*max_estimate),
+            Violation::TooFewVoters { min_voters, voters } => {
+                (texts.too_few_voters)(*voters, *min_voters)
+            }
+            Violation::MissingComments(names) => (texts.missing_comments)(&names.join(", ")),
+        }
+    }
+}
+
+/// Evaluates the chat rules against the votes of a finished session.
+pub fn evaluate(
+    config: &ChatConfig,
+    votes: &[EstimationWithUser],
+    anonymous: bool,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    if let Some(max_estimate) = config.max_estimate {
+        if votes.iter().any(|vote| upper(vote) > max_estimate) {
+            violations.push(Violation::EstimateTooBig { max_estimate });
+        }
+    }
+
+    if let Some(min_voters) = config.min_voters {
+        if votes.len() < min_voters {
+            violations.push(Violation::TooFewVoters {
+                min_voters,
+                voters: votes.len(),
+            });
+        }
+    }
+
+    // Asking the outliers by name would reveal who voted what.
+    if config.outlier_comments && !anonymous {
+        let missing: Vec<String> = outliers(votes)
+            .into_iter()
+            .filter(|vote| vote.comment.is_none())
+            .map(|vote| {
+                helpers::make_username_line(&vote.first_name, &vote.last_name, &vote.username)
+            })
+            .collect();
+
+        if !missing.is_empty() {
+            violations.push(Violation::MissingComments(missing));
+        }
+    }
+
+    violations
+}
+
+/// Whether the highest and the lowest votes are further apart in the deck
+/// than the chat's policy allows.
+pub fn is_spread_exceeded(config: &ChatConfig, deck: Deck, votes: &[EstimationWithUser]) -> bool {
+    let values: Vec<usize> = votes
+        .iter()
+        .flat_map(|vote| [vote.value, upper(vote)])
+        .collect();
+
+    match (
+        config.max_spread,
+        helpers::spread_in_cards(deck.cards(), &values),
+    ) {
+        (Some(max_spread), Some(spread)) => spread > max_spread,
+        _ => false,
+    }
+}
+
+/// Voters with the lowest and the highest estimates, none when everybody
+/// agrees.
+pub fn outliers(votes: &[EstimationWithUser]) -> Vec<&EstimationWithUser> {
+    let (Some(lowest), Some(highest)) = (
+        votes.iter().map(|vote| vote.value).min(),
+        votes.iter().map(upper).max(),
+    ) else {
+        return Vec::new();
+    };
+
+    if lowest == highest {
+        return Vec::new();
+    }
+
+    votes
+        .iter()
+        .filter(|vote| vote.value == lowest || upper(vote) == highest)
+        .collect()
+}
+
+fn upper(vote: &EstimationWithUser) -> usize {
+    vote.value_max.unwrap_or(vote.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote(name: &str, value: usize, value_max: Option<usize>) -> EstimationWithUser {
+        EstimationWithUser {
+            id: 0,
+            value,
+            value_max,
+            confidence: None,
+            comment: None,
+            first_name: name.to_string(),
+            last_name: String::new(),
+            username: String::new(),
+        }
+    }
+
+    #[test]
+    fn spread_is_counted_in_cards() {
+        let config = ChatConfig {
+            max_spread: Some(2),
+            ..ChatConfig::default()
+        };
+
+        // 3 and 8 are two cards apart in the Fibonacci deck, 2 and 8 three.
+        let votes = [vote("Ann", 3, None), vote("Bob", 8, None)];
+        assert!(!is_spread_exceeded(&config, Deck::Fibonacci, &votes));
+
+        let votes = [vote("Ann", 2, None), vote("Bob", 8, None)];
+        assert!(is_spread_exceeded(&config, Deck::Fibonacci, &votes));
+
+        // The upper bound of a range vote counts too.
+        let votes = [vote("Ann", 2, Some(3)), vote("Bob", 3, Some(13))];
+        assert!(is_spread_exceeded(&config, Deck::Fibonacci, &votes));
+    }
+
+    #[test]
+    fn spread_without_policy_is_never_exceeded() {
+        let votes = [vote("Ann", 1, None), vote("Bob", 100, None)];
+
+        assert!(!is_spread_exceeded(
+            &ChatConfig::default(),
+            Deck::Fibonacci,
+            &votes
+        ));
+    }
+
+    #[test]
+    fn outliers_are_the_lowest_and_the_highest_voters() {
+        let votes = [
+            vote("Ann", 2, None),
+            vote("Bob", 3, None),
+            vote("Cid", 3, Some(8)),
+            vote("Dan", 2, None),
+        ];
+        let names: Vec<&str> = outliers(&votes)
+            .iter()
+            .map(|vote| vote.first_name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["Ann", "Cid", "Dan"]);
+    }
+
+    #[test]
+    fn agreement_has_no_outliers() {
+        let votes = [vote("Ann", 5, None), vote("Bob", 5, None)];
+
+        assert!(outliers(&votes).is_empty());
+    }
+
+    #[test]
+    fn reports_every_broken_rule() {
+        let config = ChatConfig {
+            max_estimate: Some(8),
+            min_voters: Some(3),
+            outlier_comments: true,
+            ..ChatConfig::default()
+        };
+        let votes = [vote("Ann", 2, None), vote("Bob", 13, None)];
+
+        let violations = evaluate(&config, &votes, false);
+
+        assert!(matches!(
+            violations[..],
+            [
+                Violation::EstimateTooBig { max_estimate: 8 },
+                Violation::TooFewVoters {
+                    min_voters: 3,
+                    voters: 2
+                },
+                Violation::MissingComments(ref names),
+            ] if names == &["Ann", "Bob"]
+        ));
+    }
+
+    #[test]
+    fn commented_outliers_are_not_reported() {
+        let config = ChatConfig {
+            outlier_comments: true,
+            ..ChatConfig::default()
+        };
+        let mut votes = [vote("Ann", 2, None), vote("Bob", 13, None)];
+        votes[0].comment = Some("Reuses the importer".to_string());
+
+        let violations = evaluate(&config, &votes, false);
+
+        assert!(matches!(
+            violations[..],
+            [Violation::MissingComments(ref names)] if names == &["Bob"]
+        ));
+    }
+
+    #[test]
+    fn anonymous_sessions_do_not_ask_outliers() {
+        let config = ChatConfig {
+            outlier_comments: true,
+            ..ChatConfig::default()
+        };
+        let votes = [vote("Ann", 2, None), vote("Bob", 13, None)];
+
+        assert!(evaluate(&config, &votes, true).is_empty());
+    }
+}
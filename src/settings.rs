@@ -0,0 +1,400 @@
+use crate::constants::TIMER_OPTIONS;
+use crate::db::{self, ChatConfig, Deck, Language, Permissions, RevealMode, VoteMode};
+use crate::helpers::{self, InTopic};
+use crate::i18n::{self, Texts};
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+use tokio_rusqlite::Connection;
+
+const AUTO_REVEAL_OPTIONS: [Option<usize>; 6] = [None, Some(3), Some(4), Some(5), Some(6), Some(8)];
+
+/// Applies `/config <key> <value>` to the chat configuration and returns the
+/// confirmation, or `None` when the arguments are not recognised.
+pub fn apply_config_command(
+    config: &mut ChatConfig,
+    payload: &str,
+    texts: &Texts,
+) -> Option<String> {
+    let mut args = payload.split_whitespace();
+    let (key, value) = (args.next()?, args.next()?);
+
+    let reply = match key {
+        "mode" => {
+            config.vote_mode = value.parse().ok()?;
+
+            (texts.vote_mode_set)(vote_mode_label(config.vote_mode, texts))
+        }
+        "spread" => {
+            config.max_spread = parse_limit(value)?;
+
+            match config.max_spread {
+                Some(max_spread) => (texts.spread_set)(max_spread),
+                None => texts.spread_off.to_string(),
+            }
+        }
+        "max" => {
+            config.max_estimate = parse_limit(value)?;
+
+            match config.max_estimate {
+                Some(max_estimate) => (texts.max_estimate_set)(max_estimate),
+                None => texts.max_estimate_off.to_string(),
+            }
+        }
+        "voters" => {
+            config.min_voters = parse_limit(value)?;
+
+            match config.min_voters {
+                Some(min_voters) => (texts.min_voters_set)(min_voters),
+                None => texts.min_voters_off.to_string(),
+            }
+        }
+        "comments" => {
+            config.outlier_comments = parse_switch(value)?;
+
+            if config.outlier_comments {
+                texts.comments_on.to_string()
+            } else {
+                texts.comments_off.to_string()
+            }
+        }
+        _ => return None,
+    };
+
+    Some(reply)
+}
+
+pub fn is_settings_data(data: &str) -> bool {
+    data.starts_with("set_")
+}
+
+pub async fn show(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    texts: &Texts,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = msg.chat.id;
+    let config = conn
+        .call(move |conn| db::find_chat_config(conn, chat_id))
+        .await?;
+
+    bot.send_message(chat_id, texts.settings_menu)
+        .reply_markup(make_keyboard(&config, texts))
+        .in_topic(helpers::topic_id(&msg))
+        .await?;
+
+    Ok(())
+}
+
+/// Switches the pressed setting to its next value and updates the menu in
+/// place.
+pub async fn callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let CallbackQuery {
+        id: callback_id,
+        data,
+        from: user,
+        message,
+        ..
+    } = q;
+    let message = message.unwrap();
+    let key = data.unwrap_or_default();
+
+    let chat_id = message.chat.id;
+    let mut config = conn
+        .call(move |conn| db::find_chat_config(conn, chat_id))
+        .await?;
+
+    if !crate::is_chat_admin(&bot, &message.chat, user.id).await? {
+        let texts = i18n::texts(i18n::resolve(config.language, Some(&user)));
+        bot.answer_callback_query(callback_id)
+            .text(texts.admin_only)
+            .show_alert(true)
+            .await?;
+
+        return Ok(());
+    }
+
+    bot.answer_callback_query(callback_id).await?;
+
+    match key.as_str() {
+        "set_deck" => config.deck = next_option(&Deck::ALL, config.deck),
+        "set_lang" => {
+            config.language = next_option(
+                &[None, Some(Language::Ru), Some(Language::En)],
+                config.language,
+            )
+        }
+        "set_reveal" => {
+            config.reveal_mode =
+                next_option(&[RevealMode::Hidden, RevealMode::Open], config.reveal_mode)
+        }
+        "set_auto" => config.auto_reveal = next_option(&AUTO_REVEAL_OPTIONS, config.auto_reveal),
+        "set_perm" => {
+            config.permissions = next_option(
+                &[
+                    Permissions::Initiator,
+                    Permissions::InitiatorAndAdmins,
+                    Permissions::Everyone,
+                ],
+                config.permissions,
+            )
+        }
+        "set_timer" => config.timer_minutes = next_option(&TIMER_OPTIONS, config.timer_minutes),
+        "set_anon" => config.anonymous = !config.anonymous,
+        "set_close" => {
+            let texts = i18n::texts(i18n::resolve(config.language, Some(&user)));
+            bot.edit_message_text(chat_id, message.id, render_summary(&config, texts))
+                .await?;
+
+            return Ok(());
+        }
+        _ => return Ok(()),
+    }
+
+    // Resolved after the switch so a new language is applied to the menu at once.
+    let texts = i18n::texts(i18n::resolve(config.language, Some(&user)));
+    let keyboard = make_keyboard(&config, texts);
+
+    conn.call(move |conn| db::save_chat_config(conn, chat_id, config))
+        .await?;
+
+    bot.edit_message_text(chat_id, message.id, texts.settings_menu)
+        .reply_markup(keyboard)
+        .await?;
+
+    Ok(())
+}
+
+/// Settings shown in the menu: the name, the current value and the callback
+/// data of the button switching it.
+fn entries(config: &ChatConfig, texts: &Texts) -> Vec<(&'static str, String, &'static str)> {
+    vec![
+        (
+            texts.deck_setting,
+            deck_label(config.deck, texts).to_string(),
+            "set_deck",
+        ),
+        (
+            texts.language_setting,
+            language_label(config.language, texts).to_string(),
+            "set_lang",
+        ),
+        (
+            texts.reveal_setting,
+            reveal_mode_label(config.reveal_mode, texts).to_string(),
+            "set_reveal",
+        ),
+        (
+            texts.auto_reveal_setting,
+            match config.auto_reveal {
+                Some(auto_reveal) => (texts.auto_reveal_value)(auto_reveal),
+                None => texts.off.to_string(),
+            },
+            "set_auto",
+        ),
+        (
+            texts.permissions_setting,
+            permissions_label(config.permissions, texts).to_string(),
+            "set_perm",
+        ),
+        (
+            texts.timer_setting,
+            match config.timer_minutes {
+                Some(minutes) => (texts.timer_value)(minutes),
+                None => texts.off.to_string(),
+            },
+            "set_timer",
+        ),
+        (
+            texts.anonymous_setting,
+            if config.anonymous {
+                texts.yes
+            } else {
+                texts.no
+            }
+            .to_string(),
+            "set_anon",
+        ),
+    ]
+}
+
+fn render_summary(config: &ChatConfig, texts: &Texts) -> String {
+    let lines: Vec<String> = entries(config, texts)
+        .iter()
+        .map(|(name, value, _)| format!("{}: {}", name, value))
+        .collect();
+
+    format!("{}\n\n{}", texts.settings_saved, lines.join("\n"))
+}
+
+fn make_keyboard(config: &ChatConfig, texts: &Texts) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = entries(config, texts)
+        .into_iter()
+        .map(|(name, value, data)| {
+            vec![InlineKeyboardButton::callback(
+                format!("{}: {}", name, value),
+                data,
+            )]
+        })
+        .collect();
+
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        texts.settings_done_button,
+        "set_close",
+    )]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// The option following `current`, wrapping around to the first one.
+fn next_option<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let idx = options
+        .iter()
+        .position(|option| *option == current)
+        .map_or(0, |idx| idx + 1);
+
+    options[idx % options.len()]
+}
+
+pub fn deck_label(deck: Deck, texts: &Texts) -> &'static str {
+    match deck {
+        Deck::Fibonacci => texts.deck_fibonacci,
+        Deck::Modified => texts.deck_modified,
+        Deck::PowersOfTwo => texts.deck_powers_of_two,
+    }
+}
+
+/// Languages are named in themselves so they can be found in any of them.
+fn language_label(language: Option<Language>, texts: &Texts) -> &'static str {
+    match language {
+        Some(Language::Ru) => "русский",
+        Some(Language::En) => "English",
+        None => texts.language_auto,
+    }
+}
+
+fn reveal_mode_label(reveal_mode: RevealMode, texts: &Texts) -> &'static str {
+    match reveal_mode {
+        RevealMode::Hidden => texts.reveal_hidden,
+        RevealMode::Open => texts.reveal_open,
+    }
+}
+
+fn permissions_label(permissions: Permissions, texts: &Texts) -> &'static str {
+    match permissions {
+        Permissions::Initiator => texts.permissions_initiator,
+        Permissions::InitiatorAndAdmins => texts.permissions_admins,
+        Permissions::Everyone => texts.permissions_everyone,
+    }
+}
+
+fn vote_mode_label(vote_mode: VoteMode, texts: &Texts) -> &'static str {
+    match vote_mode {
+        VoteMode::Single => texts.vote_mode_single,
+        VoteMode::Range => texts.vote_mode_range,
+    }
+}
+
+/// `off` disables a limit, a number sets it.
+fn parse_limit(value: &str) -> Option<Option<usize>> {
+    match value {
+        "off" => Some(None),
+        _ => value.parse().ok().map(Some),
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts() -> &'static Texts {
+        i18n::texts(Language::En)
+    }
+
+    #[test]
+    fn applies_config_commands() {
+        let mut config = ChatConfig::default();
+
+        assert!(apply_config_command(&mut config, "mode range", texts()).is_some());
+        assert!(apply_config_command(&mut config, "spread 2", texts()).is_some());
+        assert!(apply_config_command(&mut config, "max 13", texts()).is_some());
+        assert!(apply_config_command(&mut config, "voters 3", texts()).is_some());
+        assert!(apply_config_command(&mut config, "comments on", texts()).is_some());
+
+        assert_eq!(config.vote_mode, VoteMode::Range);
+        assert_eq!(config.max_spread, Some(2));
+        assert_eq!(config.max_estimate, Some(13));
+        assert_eq!(config.min_voters, Some(3));
+        assert!(config.outlier_comments);
+    }
+
+    #[test]
+    fn off_disables_limits_and_switches() {
+        let mut config = ChatConfig {
+            max_spread: Some(2),
+            outlier_comments: true,
+            ..ChatConfig::default()
+        };
+
+        assert_eq!(
+            apply_config_command(&mut config, "spread off", texts()),
+            Some(texts().spread_off.to_string())
+        );
+        assert_eq!(
+            apply_config_command(&mut config, "comments off", texts()),
+            Some(texts().comments_off.to_string())
+        );
+        assert_eq!(config.max_spread, None);
+        assert!(!config.outlier_comments);
+    }
+
+    #[test]
+    fn ignores_unknown_keys_and_values() {
+        let mut config = ChatConfig::default();
+
+        for payload in [
+            "",
+            "mode",
+            "mode both",
+            "spread -1",
+            "comments yes",
+            "deck powers",
+        ] {
+            assert_eq!(apply_config_command(&mut config, payload, texts()), None);
+        }
+
+        assert_eq!(config.vote_mode, VoteMode::Single);
+        assert_eq!(config.max_spread, None);
+        assert!(!config.outlier_comments);
+    }
+
+    #[test]
+    fn next_option_wraps_around() {
+        let options = [None, Some(3), Some(5)];
+
+        assert_eq!(next_option(&options, None), Some(3));
+        assert_eq!(next_option(&options, Some(3)), Some(5));
+        assert_eq!(next_option(&options, Some(5)), None);
+    }
+
+    #[test]
+    fn next_option_of_an_unlisted_value_is_the_first_one() {
+        assert_eq!(next_option(&AUTO_REVEAL_OPTIONS, Some(7)), None);
+        assert_eq!(next_option(&Deck::ALL, Deck::Fibonacci), Deck::ALL[1]);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::db::{self, SessionVote, VoterActivity};
+use crate::helpers::{self, InTopic};
+use crate::i18n::Texts;
+use std::collections::HashMap;
+use std::error::Error;
+use teloxide::prelude::*;
+use tokio_rusqlite::Connection;
+
+/// Mean relative deviation, in percent, below which a voter is considered
+/// neither an over- nor an under-estimator.
+const TENDENCY_THRESHOLD: f64 = 10.0;
+
+#[derive(Debug, Default)]
+struct VoterStats {
+    /// Relative deviations from the session median, in percent.
+    deviations: Vec<f64>,
+    outlier_count: usize,
+}
+
+pub async fn show(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    texts: &Texts,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = msg.chat.id;
+
+    let (activity, votes) = conn
+        .call(move |conn| {
+            Ok((
+                db::find_voter_activity(conn, chat_id)?,
+                db::find_session_votes(conn, chat_id)?,
+            ))
+        })
+        .await?;
+
+    bot.send_message(chat_id, render(&activity, &votes, texts))
+        .in_topic(helpers::topic_id(&msg))
+        .await?;
+
+    Ok(())
+}
+
+fn collect(votes: &[SessionVote]) -> HashMap<usize, VoterStats> {
+    let mut sessions: HashMap<usize, Vec<&SessionVote>> = HashMap::new();
+    for vote in votes {
+        sessions.entry(vote.session_id).or_default().push(vote);
+    }
+
+    let mut stats: HashMap<usize, VoterStats> = HashMap::new();
+
+    for session_votes in sessions.values() {
+        let midpoints: Vec<f64> = session_votes.iter().map(|vote| midpoint(vote)).collect();
+        let median = helpers::median(&midpoints).unwrap_or_default();
+        let lowest = session_votes.iter().map(|vote| vote.value).min();
+        let highest = session_votes.iter().map(|vote| upper(vote)).max();
+
+        for vote in session_votes {
+            let voter = stats.entry(vote.user_db_id).or_default();
+
+            if median > 0.0 {
+                voter
+                    .deviations
+                    .push((midpoint(vote) - median) / median * 100.0);
+            }
+
+            if lowest != highest && (Some(vote.value) == lowest || Some(upper(vote)) == highest) {
+                voter.outlier_count += 1;
+            }
+        }
+    }
+
+    stats
+}
+
+fn render(activity: &[VoterActivity], votes: &[SessionVote], texts: &Texts) -> String {
+    if activity.is_empty() {
+        return texts.no_stats.to_string();
+    }
+
+    let stats = collect(votes);
+
+    let lines: Vec<String> = activity
+        .iter()
+        .map(|voter| {
+            let name =
+                helpers::make_username_line(&voter.first_name, &voter.last_name, &voter.username);
+            let voter_stats = stats.get(&voter.user_db_id);
+
+            let deviation_line = match voter_stats.map(|stats| &stats.deviations) {
+                Some(deviations) if !deviations.is_empty() => {
+                    let count = deviations.len() as f64;
+                    let average = deviations.iter().map(|d| d.abs()).sum::<f64>() / count;
+                    let tendency = deviations.iter().sum::<f64>() / count;
+
+                    (texts.deviation)(average, &describe_tendency(tendency, texts))
+                }
+                _ => texts.deviation_unknown.to_string(),
+            };
+
+            (texts.stats_line)(
+                &name,
+                voter.sessions_voted,
+                &deviation_line,
+                voter_stats.map_or(0, |stats| stats.outlier_count),
+            )
+        })
+        .collect();
+
+    format!("{}\n\n{}", texts.stats_heading, lines.join("\n\n"))
+}
+
+fn describe_tendency(tendency: f64, texts: &Texts) -> String {
+    if tendency > TENDENCY_THRESHOLD {
+        (texts.overestimates)(tendency)
+    } else if tendency < -TENDENCY_THRESHOLD {
+        (texts.underestimates)(tendency)
+    } else {
+        texts.no_bias.to_string()
+    }
+}
+
+fn midpoint(vote: &SessionVote) -> f64 {
+    (vote.value + upper(vote)) as f64 / 2.0
+}
+
+fn upper(vote: &SessionVote) -> usize {
+    vote.value_max.unwrap_or(vote.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Language;
+    use crate::i18n;
+
+    fn vote(
+        session_id: usize,
+        user_db_id: usize,
+        value: usize,
+        value_max: Option<usize>,
+    ) -> SessionVote {
+        SessionVote {
+            session_id,
+            user_db_id,
+            value,
+            value_max,
+        }
+    }
+
+    fn sorted_deviations(stats: &HashMap<usize, VoterStats>, user_db_id: usize) -> Vec<i64> {
+        let mut deviations: Vec<i64> = stats[&user_db_id]
+            .deviations
+            .iter()
+            .map(|deviation| deviation.round() as i64)
+            .collect();
+        deviations.sort();
+
+        deviations
+    }
+
+    fn votes() -> Vec<SessionVote> {
+        vec![
+            // Median 3.
+            vote(1, 1, 3, None),
+            vote(1, 2, 3, None),
+            vote(1, 3, 5, None),
+            // Median 4, the middle of the 3–5 range.
+            vote(2, 1, 2, None),
+            vote(2, 2, 3, Some(5)),
+            vote(2, 3, 8, None),
+            // Everybody agrees.
+            vote(3, 1, 5, None),
+            vote(3, 2, 5, None),
+            vote(3, 3, 5, None),
+            // Zero median, nothing to compare with.
+            vote(4, 1, 0, None),
+            vote(4, 3, 0, None),
+        ]
+    }
+
+    #[test]
+    fn deviations_are_relative_to_the_session_median() {
+        let stats = collect(&votes());
+
+        assert_eq!(sorted_deviations(&stats, 1), vec![-50, 0, 0]);
+        assert_eq!(sorted_deviations(&stats, 2), vec![0, 0, 0]);
+        assert_eq!(sorted_deviations(&stats, 3), vec![0, 67, 100]);
+    }
+
+    #[test]
+    fn outliers_are_counted_per_session() {
+        let stats = collect(&votes());
+
+        assert_eq!(stats[&1].outlier_count, 2);
+        assert_eq!(stats[&2].outlier_count, 1);
+        assert_eq!(stats[&3].outlier_count, 2);
+    }
+
+    #[test]
+    fn tendency_ignores_small_deviations() {
+        let texts = i18n::texts(Language::En);
+
+        assert_eq!(
+            describe_tendency(55.6, texts),
+            "tends to overestimate (+56%)"
+        );
+        assert_eq!(
+            describe_tendency(-16.7, texts),
+            "tends to underestimate (-17%)"
+        );
+        assert_eq!(describe_tendency(5.0, texts), texts.no_bias);
+        assert_eq!(describe_tendency(-10.0, texts), texts.no_bias);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::db;
+use std::error::Error;
+use std::time::Duration;
+use teloxide::prelude::*;
+use tokio_rusqlite::Connection;
+
+/// How often open sessions are checked for a timer that ran out.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Finishes sessions whose timer has run out, for as long as the bot runs.
+pub async fn run(bot: Bot, conn: Connection) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let sessions = match conn.call(db::find_expired_sessions).await {
+            Ok(sessions) => sessions,
+            Err(error) => {
+                log::error!("Failed to look up expired sessions: {}", error);
+                continue;
+            }
+        };
+
+        for session in sessions {
+            let session_id = session.session_id;
+
+            if let Err(error) = finish(&bot, &conn, session).await {
+                log::error!(
+                    "Failed to finish session {} by timer: {}",
+                    session_id,
+                    error
+                );
+            }
+        }
+    }
+}
+
+async fn finish(
+    bot: &Bot,
+    conn: &Connection,
+    mut session: db::SessionWithInitiator,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = session.chat_id();
+    let config = conn
+        .call(move |conn| db::find_session_config(conn, chat_id))
+        .await?;
+
+    crate::finish_session(bot.clone(), conn, &mut session, &config).await
+}
@@ -0,0 +1,316 @@
+use crate::constants::TIMER_OPTIONS;
+use crate::db::{self, DbUser, Deck, Dialogue, DialogueStep, Language, SessionMessage};
+use crate::helpers::{self, InTopic};
+use crate::i18n::{self, Texts};
+use crate::settings;
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{ForceReply, InlineKeyboardButton, InlineKeyboardMarkup, MessageId, User},
+    RequestError,
+};
+use tokio_rusqlite::Connection;
+
+/// Answer to the description prompt that leaves the description empty.
+const SKIP_ANSWER: &str = "-";
+
+pub fn is_wizard_data(data: &str) -> bool {
+    data.starts_with("wiz_")
+}
+
+/// `/poker` without a task asks for it step by step: the title and the
+/// description are answered by replying to the prompts, the deck and the
+/// timer with buttons. The dialogue is kept in the database, so it survives
+/// a restart of the bot.
+pub async fn start(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+    language: Language,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let prompt = ask(&bot, &msg, i18n::texts(language).wizard_title_prompt).await?;
+
+    let dialogue = Dialogue {
+        chat_id: msg.chat.id,
+        user_id: msg.from().unwrap().id,
+        step: DialogueStep::Title,
+        prompt_message_id: prompt.id,
+        thread_id: helpers::topic_id(&msg),
+        title: String::new(),
+        description: String::new(),
+        deck: None,
+        language,
+    };
+
+    conn.call(move |conn| db::save_dialogue(conn, dialogue))
+        .await?;
+
+    Ok(())
+}
+
+/// Whether the message answers the prompt of a dialogue.
+pub async fn is_dialogue_reply(msg: Message, conn: Connection) -> bool {
+    let Some(reply) = msg.reply_to_message() else {
+        return false;
+    };
+
+    if msg.text().is_none() {
+        return false;
+    }
+
+    let (chat_id, prompt_message_id) = (msg.chat.id, reply.id);
+
+    conn.call(move |conn| db::find_dialogue(conn, chat_id, prompt_message_id))
+        .await
+        .is_ok_and(|dialogue| dialogue.is_some())
+}
+
+pub async fn reply_handler(
+    bot: Bot,
+    msg: Message,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (chat_id, prompt_message_id) = (msg.chat.id, msg.reply_to_message().unwrap().id);
+    let Some(mut dialogue) = conn
+        .call(move |conn| db::find_dialogue(conn, chat_id, prompt_message_id))
+        .await?
+    else {
+        return Ok(());
+    };
+
+    // Only the initiator answers, others may still talk in the thread.
+    if msg.from().map(|user| user.id) != Some(dialogue.user_id) {
+        return Ok(());
+    }
+
+    let texts = i18n::texts(dialogue.language);
+    let answer = msg.text().unwrap().trim();
+
+    match dialogue.step {
+        DialogueStep::Title if answer.is_empty() => {
+            dialogue.prompt_message_id = ask(&bot, &msg, texts.wizard_title_prompt).await?.id;
+        }
+        DialogueStep::Title => {
+            let (title, description) = helpers::parse_title_and_description(answer);
+            dialogue.title = title;
+            dialogue.description = description.trim().to_string();
+
+            // A multiline answer already brings the description.
+            if dialogue.description.is_empty() {
+                dialogue.step = DialogueStep::Description;
+                dialogue.prompt_message_id =
+                    ask(&bot, &msg, texts.wizard_description_prompt).await?.id;
+            } else {
+                ask_deck(&bot, &msg, &mut dialogue, texts).await?;
+            }
+        }
+        DialogueStep::Description => {
+            if answer != SKIP_ANSWER {
+                dialogue.description = answer.to_string();
+            }
+
+            ask_deck(&bot, &msg, &mut dialogue, texts).await?;
+        }
+        // Answered with the buttons.
+        DialogueStep::Deck | DialogueStep::Timer => return Ok(()),
+    }
+
+    conn.call(move |conn| db::save_dialogue(conn, dialogue))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    conn: Connection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (Some(message), Some(data)) = (q.message, q.data) else {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    };
+
+    let (chat_id, prompt_message_id) = (message.chat.id, message.id);
+    let Some(mut dialogue) = conn
+        .call(move |conn| db::find_dialogue(conn, chat_id, prompt_message_id))
+        .await?
+    else {
+        let config = conn
+            .call(move |conn| db::find_chat_config(conn, chat_id))
+            .await?;
+        let texts = i18n::texts(i18n::resolve(config.language, Some(&q.from)));
+
+        bot.answer_callback_query(q.id)
+            .text(texts.wizard_expired)
+            .show_alert(true)
+            .await?;
+        bot.edit_message_reply_markup(chat_id, prompt_message_id)
+            .await?;
+
+        return Ok(());
+    };
+
+    let texts = i18n::texts(dialogue.language);
+
+    if q.from.id != dialogue.user_id {
+        bot.answer_callback_query(q.id)
+            .text(texts.wizard_not_yours)
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(q.id).await?;
+
+    match (dialogue.step, data.strip_prefix("wiz_")) {
+        (DialogueStep::Deck, Some(choice)) => {
+            let Some(deck) = choice
+                .strip_prefix("deck_")
+                .and_then(|deck| deck.parse().ok())
+            else {
+                return Ok(());
+            };
+
+            dialogue.deck = Some(deck);
+            dialogue.step = DialogueStep::Timer;
+
+            bot.edit_message_text(chat_id, prompt_message_id, texts.wizard_timer_prompt)
+                .reply_markup(timer_keyboard(texts))
+                .await?;
+
+            conn.call(move |conn| db::save_dialogue(conn, dialogue))
+                .await?;
+        }
+        (DialogueStep::Timer, Some(choice)) => {
+            let Some(timer_minutes) = choice.strip_prefix("timer_").and_then(parse_timer) else {
+                return Ok(());
+            };
+
+            finish(&bot, &conn, dialogue, q.from, timer_minutes).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Starts the session of a completed dialogue with the picked deck and
+/// timer, the other settings come from the chat.
+async fn finish(
+    bot: &Bot,
+    conn: &Connection,
+    dialogue: Dialogue,
+    user: User,
+    timer_minutes: Option<usize>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = dialogue.chat_id;
+    let initiator = DbUser {
+        first_name: user.first_name.clone(),
+        last_name: user.last_name.clone().unwrap_or_default(),
+        username: user.username.clone().unwrap_or_default(),
+    };
+
+    // Deleting the dialogue first makes a second click on the timer a no-op
+    // instead of starting one more session.
+    let Some((mut config, initiator_db_id)) = conn
+        .call(move |conn| {
+            if db::delete_dialogue(conn, chat_id, user.id)? == 0 {
+                return Ok(None);
+            }
+
+            Ok(Some((
+                db::find_chat_config(conn, chat_id)?,
+                db::upsert_user(conn, &user)?,
+            )))
+        })
+        .await?
+    else {
+        return Ok(());
+    };
+
+    config.deck = dialogue.deck.unwrap_or(config.deck);
+    config.timer_minutes = timer_minutes;
+
+    // The prompt is of no use once the session message is posted.
+    let _ = bot
+        .delete_message(chat_id, dialogue.prompt_message_id)
+        .await;
+
+    let session = crate::new_session(
+        &config,
+        initiator_db_id,
+        initiator,
+        (dialogue.title, dialogue.description),
+        dialogue.language,
+        SessionMessage::Chat(chat_id, MessageId(0)),
+        dialogue.thread_id,
+    );
+
+    crate::post_session(bot, conn, session, &config).await?;
+
+    Ok(())
+}
+
+/// Asks a question the user answers by replying. ForceReply opens the reply
+/// only for the user the prompt replies to.
+async fn ask(bot: &Bot, msg: &Message, text: &str) -> Result<Message, RequestError> {
+    bot.send_message(msg.chat.id, text)
+        .reply_to_message_id(msg.id)
+        .reply_markup(ForceReply::new().selective(true))
+        .in_topic(helpers::topic_id(msg))
+        .await
+}
+
+async fn ask_deck(
+    bot: &Bot,
+    msg: &Message,
+    dialogue: &mut Dialogue,
+    texts: &Texts,
+) -> Result<(), RequestError> {
+    let keyboard = InlineKeyboardMarkup::new(Deck::ALL.iter().map(|deck| {
+        vec![InlineKeyboardButton::callback(
+            settings::deck_label(*deck, texts),
+            format!("wiz_deck_{}", deck.as_str()),
+        )]
+    }));
+
+    let prompt = bot
+        .send_message(msg.chat.id, texts.wizard_deck_prompt)
+        .reply_to_message_id(msg.id)
+        .reply_markup(keyboard)
+        .in_topic(helpers::topic_id(msg))
+        .await?;
+
+    dialogue.step = DialogueStep::Deck;
+    dialogue.prompt_message_id = prompt.id;
+
+    Ok(())
+}
+
+fn timer_keyboard(texts: &Texts) -> InlineKeyboardMarkup {
+    let row = TIMER_OPTIONS
+        .iter()
+        .map(|option| match option {
+            Some(minutes) => InlineKeyboardButton::callback(
+                (texts.timer_value)(*minutes),
+                format!("wiz_timer_{}", minutes),
+            ),
+            None => InlineKeyboardButton::callback(texts.off, "wiz_timer_off"),
+        })
+        .collect::<Vec<_>>();
+
+    InlineKeyboardMarkup::new(row.chunks(3).map(|chunk| chunk.to_vec()))
+}
+
+/// `Some(None)` for no timer, `None` for anything that is not an option.
+fn parse_timer(raw: &str) -> Option<Option<usize>> {
+    match raw {
+        "off" => Some(None),
+        minutes => minutes
+            .parse()
+            .ok()
+            .filter(|minutes| TIMER_OPTIONS.contains(&Some(*minutes)))
+            .map(Some),
+    }
+}